@@ -14,29 +14,48 @@ use mirl::platform::framework_traits::Framework;
 // use mirl::platform::mouse::position::RawMouseInputTrait;
 //use mirl::platform::framework_traits::Framework;
 use mirl::platform::Buffer;
-use mirl::platform::FileSystem;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::internal::id::UsizeGetID;
-use crate::internal::BlockInput;
 use crate::internal::WorkSpace;
 use crate::logic::Physics;
+use crate::plugin::TypeHierarchy;
 
 use crate::internal::Block;
+use crate::internal::BlockList;
+use crate::internal::BlockKind;
+use crate::internal::block_input::LITERAL_PADDING;
+use crate::internal::container::CONTAINER_ARM_WIDTH;
+use crate::internal::spatial_grid::GRID_CELL_SIZE;
+use crate::internal::dropdown::{Dropdown, DropdownOutcome};
+use crate::internal::literal_editor::EditOutcome;
+use crate::internal::palette::Palette;
 use crate::internal::Camera;
+use crate::internal::camera::ZOOM_STEP;
+use crate::internal::LiteralEditor;
 use crate::internal::ID;
 use crate::idk::draw_block;
+use crate::idk::render_dropdown;
+use crate::idk::render_literal_editor;
+use crate::idk::render_messages;
+use crate::idk::render_palette;
+use crate::idk::render_trash;
+use crate::idk::trash_area;
 use crate::CoordinateType;
 use crate::SizeType;
 use mirl::extensions::*;
 
 
-
+/// How close a dragged block has to come to a block, input or slot to snap to it, in screen pixels
+pub const SNAP_DISTANCE: SizeType = 70.0;
 
 type KeyMap = HashMap<&'static str, Vec<mirl::platform::KeyCode>>;
 static KEY_BINDS: std::sync::LazyLock<std::sync::Mutex<KeyMap>> = std::sync::LazyLock::new(|| {
     std::sync::Mutex::new(KeyMap::new())
 });
+static KEY_BINDS_LAST_DOWN: std::sync::LazyLock<std::sync::Mutex<HashMap<&'static str, bool>>> = std::sync::LazyLock::new(|| {
+    std::sync::Mutex::new(HashMap::new())
+});
 
 
 /// Adds or updates a key. Returns the previous value if any.
@@ -86,6 +105,16 @@ pub fn is_key_bind_down<F:mirl::platform::framework_traits::Framework>(key: &'st
     }
     false
 }
+/// Only true on the frame the key bind went down - Ask once per frame or presses get swallowed
+pub fn was_key_bind_pressed<F:mirl::platform::framework_traits::Framework>(key: &'static str, framework: &F)->bool{
+    let down = is_key_bind_down(key, framework);
+    let previous = KEY_BINDS_LAST_DOWN
+        .lock()
+        .ok()
+        .and_then(|mut map| map.insert(key, down))
+        .unwrap_or_default();
+    down && !previous
+}
 
 
 
@@ -118,7 +147,7 @@ fn render_block<L: Physics>(
 
 
 
-pub fn get_top_most_block_idx_or_self(blocks: &[Block], index: usize) -> usize {
+pub fn get_top_most_block_idx_or_self(blocks: &BlockList, index: usize) -> usize {
     let block = &blocks[index];
     if let Some(above_connection) = block.connected_above.get(){
         if let Some(idx) = index_by_block_id(&above_connection, blocks)
@@ -127,7 +156,7 @@ pub fn get_top_most_block_idx_or_self(blocks: &[Block], index: usize) -> usize {
     index
 }
 
-pub fn get_bottom_most_block_idx_or_self(blocks: &[Block], index: usize) -> usize {
+pub fn get_bottom_most_block_idx_or_self(blocks: &BlockList, index: usize) -> usize {
     let block = &blocks[index];
     if let Some(block_below) = block.connected_below.get() {
         if let Some(idx) = index_by_block_id(&block_below, blocks)
@@ -142,19 +171,20 @@ pub fn get_bottom_most_block_idx_or_self(blocks: &[Block], index: usize) -> usiz
 fn handle_connection_and_render_ghost_block<L: Physics, F:Framework>(
     buffer: &Buffer,
     camera: &Camera,
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     font: &Font,
     selected: &Option<usize>,
     snap_distance: SizeType,
     logic: &L,
     block_colors: &[u32],
-    selected_type_is_action: bool,
-    framework: &F
+    selected_kind: BlockKind,
+    framework: &F,
+    types: &TypeHierarchy,
 ) {
     if let Some(selected) = *selected {
-        if selected_type_is_action {
+        if selected_kind.is_stackable() {
             // Connect to block above
-            let possible_connection = logic.get_block_in_distance(
+            let mut possible_connection = logic.get_block_in_distance(
                 blocks,
                 blocks[selected].x.get() as SizeType,
                 blocks[selected].y.get() as SizeType,
@@ -164,6 +194,62 @@ fn handle_connection_and_render_ghost_block<L: Physics, F:Framework>(
             );
             let block = &blocks[selected];
             block.possible_connection_above.set(None);
+            block.possible_slot.set(None);
+
+            // A container can't go into its own slots, neither directly nor below something in there
+            let in_own_slots: Vec<ID> = get_ids_connected_below_block(block.id, blocks, &mut Vec::new())
+                .iter()
+                .filter_map(|x| index_by_block_id(x, blocks))
+                .flat_map(|x| blocks[x].get_all_ids_in_slots(blocks))
+                .collect();
+            possible_connection = possible_connection.filter(|x| !in_own_slots.contains(&blocks[*x].id));
+            let mut own_stack = get_ids_connected_below_block(block.id, blocks, &mut Vec::new());
+            own_stack.extend(&in_own_slots);
+            let possible_slot = logic
+                .get_slot_in_distance(
+                    blocks,
+                    (block.x.get() as isize, block.y.get() as isize),
+                    snap_distance,
+                    &own_stack,
+                )
+                .filter(|_| block.kind.can_connect_above());
+            if let Some(((container_id, slot), slot_distance)) = possible_slot {
+                // The closer one wins, stacks are compared top to top like get_block_in_distance does
+                let stack_distance = possible_connection.map(|x| {
+                    logic.get_distance_between_positions(
+                        (block.x.get() as isize - blocks[x].x.get() as isize) as SizeType,
+                        (block.y.get() as isize - blocks[x].y.get() as isize) as SizeType,
+                        0.0,
+                        0.0,
+                    )
+                });
+                if stack_distance.is_none_or(|x| slot_distance <= x) {
+                    block.possible_slot.set(Some((container_id, slot)));
+                    let Some(container_idx) = index_by_block_id(&container_id, blocks) else {
+                        return;
+                    };
+                    let container = &blocks[container_idx];
+                    draw_block(
+                        block,
+                        container.x.get() as isize + CONTAINER_ARM_WIDTH as isize,
+                        container.y.get() as isize + container.slot_offset_y(slot) as isize,
+                        camera,
+                        buffer,
+                        mirl::graphics::desaturate_fast(
+                            mirl::graphics::adjust_brightness_fast(
+                                block_colors[block.block_color_id],
+                                -5,
+                            ),
+                            0.91,
+                        ),
+                        buffer.width,
+                        buffer.height,
+                        font,
+                        logic,
+                    );
+                    return;
+                }
+            }
 
             if let Some(possible_connection) = possible_connection {
                 let above_block = &blocks[possible_connection];
@@ -193,7 +279,7 @@ fn handle_connection_and_render_ghost_block<L: Physics, F:Framework>(
                 draw_block(
                     block,
                     above_block.x.get() as isize,
-                    above_block.y.get() as isize + block.height.get() as isize,
+                    above_block.y.get() as isize + above_block.full_height() as isize,
                     camera,
                     buffer,
                     mirl::graphics::desaturate_fast(
@@ -209,32 +295,83 @@ fn handle_connection_and_render_ghost_block<L: Physics, F:Framework>(
                     logic,
                 );
             }
-        } else {
-            let inline_block_connection = logic.get_block_input_in_distance(
-                blocks,
-                blocks[selected].x.get() as SizeType,
-                blocks[selected].y.get() as SizeType,
-                snap_distance,
-                &mirl::lists::combined(
-                    &blocks[selected]
-                        .get_all_ids_of_all_inputs(blocks),
-                    blocks[selected].id,
-                ),
-                true,
+        } else if selected_kind.goes_into_inputs() {
+            let block = &blocks[selected];
+            let blacklisted = mirl::lists::combined(
+                &block.get_all_ids_of_all_inputs(blocks),
+                block.id,
             );
-
-            if inline_block_connection.is_some() {
-                println!("FOUND: {:?}", inline_block_connection)
+            let find_input = |accepts: &dyn Fn(&Block, usize) -> bool| {
+                logic.get_block_input_in_distance(
+                    blocks,
+                    block.x.get() as SizeType,
+                    block.y.get() as SizeType,
+                    snap_distance,
+                    &blacklisted,
+                    true,
+                    accepts,
+                )
+                // Only the innermost input of the path is where the block goes
+                .and_then(|x| x.last().copied())
+            };
+            let compatible = find_input(&|host, input_idx| {
+                types.is_assignable(&block.output, host.inputs[input_idx].input_type())
+            });
+            block.possible_input.set(compatible);
+            // Show where it would have gone so it's clear why nothing happens when letting go
+            let (target, ghost_color) = match compatible {
+                Some(target) => (
+                    Some(target),
+                    mirl::graphics::desaturate_fast(
+                        mirl::graphics::adjust_brightness_fast(
+                            block_colors[block.block_color_id],
+                            -5,
+                        ),
+                        0.91,
+                    ),
+                ),
+                None => (
+                    find_input(&|_, _| true),
+                    mirl::graphics::rgb_to_u32(120, 40, 40),
+                ),
+            };
+            let Some((host_id, input_idx)) = target else {
+                return;
+            };
+            let Some(host_idx) = index_by_block_id(&host_id, blocks) else {
+                return;
+            };
+            let host = &blocks[host_idx];
+            if !logic.is_block_visible_on_screen(
+                host,
+                camera,
+                &(buffer.width as isize),
+                &(buffer.height as isize),
+            ) {
+                return;
             }
+            let input_offset = host.input_offsets.borrow().get(input_idx * 2 + 1).copied().unwrap_or_default();
+            draw_block(
+                block,
+                host.x.get() as isize + input_offset as isize,
+                host.y.get() as isize,
+                camera,
+                buffer,
+                ghost_color,
+                buffer.width,
+                buffer.height,
+                font,
+                logic,
+            );
         }
     }
 }
 
 // Misc
-fn handle_and_render_action_blocks_on_screen<L: Physics>(
+pub fn handle_and_render_action_blocks_on_screen<L: Physics>(
     buffer: &Buffer,
     camera: &Camera,
-    blocks: &mut Vec<Block>,
+    blocks: &mut BlockList,
     block_colors: &[u32],
     font: &Font,
     logic: &L,
@@ -245,20 +382,104 @@ fn handle_and_render_action_blocks_on_screen<L: Physics>(
     // Reverse block order in order for overdraw to to its job in our favor
     let block_ids: Vec<usize> = (0..blocks.len()).rev().collect();
 
-    for id in block_ids {
-        if blocks[id].recently_moved.get() {
-            move_block_to_connected(blocks, &Some(id));
+    for id in block_ids.iter() {
+        if blocks[*id].recently_moved.get() {
+            move_block_to_connected(blocks, &Some(*id));
         }
-        let block = &blocks[id];
+    }
+    // Containers grow with their slots, which moves everything below them - Only stacks something moved in can have changed
+    let mut roots: Vec<ID> = blocks
+        .take_moved_since_layout()
+        .into_iter()
+        .filter_map(|x| get_layout_root(blocks, x))
+        .collect();
+    roots.sort_unstable();
+    roots.dedup();
+    for root in roots {
+        if let Some(block) = blocks.get_by_id(&root).filter(|x| x.is_stack_root()) {
+            block.layout_stack(blocks);
+        }
+    }
+    // What that moved is in place already
+    blocks.take_moved_since_layout();
+    // Snapping the dragged block looks for what is around it
+    blocks.refresh_spatial_index();
 
-        if !logic.is_block_visible_on_screen(
-            block,
-            camera,
-            &now_width,
-            &now_height,
-        ) {
+    for id in block_ids {
+        let block = &blocks[id];
+        // Stored blocks are drawn on top of the block they are stored in
+        if block.stored_in.get().is_some() {
             continue;
         }
+        block.position_stored_inputs(blocks);
+        render_block_with_stored_inputs(block, camera, buffer, block_colors, font, logic, blocks, &now_width, &now_height);
+    }
+}
+
+/// The block whose [`layout_stack`](Block::layout_stack) positions `id`, going out of inputs, up stacks and out of slots
+///
+/// None for blocks that don't exist or links that go in a circle
+fn get_layout_root(blocks: &BlockList, id: ID) -> Option<ID> {
+    let mut current = id;
+    let mut visited = HashSet::new();
+    while visited.insert(current) {
+        let block = blocks.get_by_id(&current)?;
+        current = if let Some((host, _)) = block.stored_in.get() {
+            host
+        } else if let Some((container, _)) = block.in_slot.get() {
+            container
+        } else if let Some(above) = block.connected_above.get() {
+            // The top skips the rest of the stack, unless it is out of date
+            block.connected_top.get().filter(|x| *x != current).unwrap_or(above)
+        } else {
+            return Some(current);
+        };
+    }
+    None
+}
+
+/// Draws the block at `selected`, what is below it and what is in its slots again, on top of everything
+fn render_dragged_stack<L: Physics>(
+    buffer: &Buffer,
+    camera: &Camera,
+    blocks: &BlockList,
+    selected: usize,
+    block_colors: &[u32],
+    font: &Font,
+    logic: &L,
+) {
+    let mut dragged = get_ids_connected_below_block(blocks[selected].id, blocks, &mut Vec::new());
+    for id in dragged.clone() {
+        if let Some(idx) = index_by_block_id(&id, blocks) {
+            dragged.extend(blocks[idx].get_all_ids_in_slots(blocks));
+        }
+    }
+    let (now_width, now_height) = (buffer.width as isize, buffer.height as isize);
+    // Same order as the workspace is drawn in
+    for block in blocks.iter().rev() {
+        if dragged.contains(&block.id) && block.stored_in.get().is_none() {
+            render_block_with_stored_inputs(block, camera, buffer, block_colors, font, logic, blocks, &now_width, &now_height);
+        }
+    }
+}
+
+fn render_block_with_stored_inputs<L: Physics>(
+    block: &Block,
+    camera: &Camera,
+    buffer: &Buffer,
+    block_colors: &[u32],
+    font: &Font,
+    logic: &L,
+    blocks: &BlockList,
+    now_width: &isize,
+    now_height: &isize,
+) {
+    if logic.is_block_visible_on_screen(
+        block,
+        camera,
+        now_width,
+        now_height,
+    ) {
         render_block(
             block,
             camera,
@@ -270,24 +491,28 @@ fn handle_and_render_action_blocks_on_screen<L: Physics>(
             logic,
         );
     }
-}
-
-#[inline]
-fn reorder_element<T>(vec: &mut Vec<T>, from: usize, to: usize) {
-    if from != to && from < vec.len() && to < vec.len() {
-        let item = vec.remove(from);
-        vec.insert(to, item)
+    for stored in block.stored_inputs.borrow().iter().flatten() {
+        if let Some(idx) = index_by_block_id(stored, blocks) {
+            render_block_with_stored_inputs(&blocks[idx], camera, buffer, block_colors, font, logic, blocks, now_width, now_height);
+        }
     }
 }
 
 // Block stuff
 fn get_block_id_above<L: Physics>(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     pos_x: SizeType,
     pos_y: SizeType,
     logic: &L,
 ) -> Option<usize> {
-    for (block_id, block) in blocks.iter().enumerate() {
+    // The area checked is as tall as the block and right below it, so the top of the block is at most twice the tallest block above the point
+    let point = (pos_x as isize, pos_y as isize);
+    let candidates = blocks.indexes_in_area(
+        (point.0 - GRID_CELL_SIZE, point.1 - blocks.tallest() * 2 - GRID_CELL_SIZE),
+        (point.0 + GRID_CELL_SIZE, point.1 + GRID_CELL_SIZE),
+    );
+    for block_id in candidates {
+        let block = &blocks[block_id];
         if logic.is_point_in_rectangle(
             pos_x,
             pos_y,
@@ -303,37 +528,68 @@ fn get_block_id_above<L: Physics>(
 }
 
 #[inline]
-fn get_block_id_under_point<L: Physics>(
-    blocks: &Vec<Block>,
+pub fn get_block_id_under_point<L: Physics>(
+    blocks: &BlockList,
     pos_x: isize,
     pos_y: isize,
     logic: &L,
 ) -> Option<usize> {
-    for (block_id, block) in blocks.iter().enumerate() {
+    for block_id in blocks.indexes_near((pos_x, pos_y), 0) {
+        let block = &blocks[block_id];
+        let hit = block.hit_rectangles().into_iter().any(|(x, y, width, height)| {
+            logic.is_point_in_rectangle(
+                pos_x,
+                pos_y,
+                block.x.get() as isize + x as isize,
+                block.y.get() as isize + y as isize,
+                width as isize,
+                height as isize,
+            )
+        });
+        if hit {
+            // Stored blocks are drawn on top, so they are the ones being clicked
+            return Some(get_stored_block_id_under_point(blocks, block_id, pos_x, pos_y, logic));
+        }
+    }
+    None
+}
+
+fn get_stored_block_id_under_point<L: Physics>(
+    blocks: &BlockList,
+    index: usize,
+    pos_x: isize,
+    pos_y: isize,
+    logic: &L,
+) -> usize {
+    for stored in blocks[index].stored_inputs.borrow().iter().flatten() {
+        let Some(stored_idx) = index_by_block_id(stored, blocks) else {
+            continue;
+        };
+        let stored_block = &blocks[stored_idx];
         if logic.is_point_in_rectangle(
             pos_x,
             pos_y,
-            block.x.get() as isize,
-            block.y.get() as isize,
-            block.width.get() as isize,
-            block.height.get() as isize,
+            stored_block.x.get() as isize,
+            stored_block.y.get() as isize,
+            stored_block.width.get() as isize,
+            stored_block.height.get() as isize,
         ) {
-            return Some(block_id);
+            return get_stored_block_id_under_point(blocks, stored_idx, pos_x, pos_y, logic);
         }
     }
-    None
+    index
 }
 
 #[inline]
 fn get_specific_block_id_under_point<L: Physics>(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     pos_x: f64,
     pos_y: f64,
     logic: &L,
-    block_type: u8,
+    kind: BlockKind,
 ) -> Option<usize> {
     for (block_id, block) in blocks.iter().enumerate() {
-        if block.block_type != block_type {
+        if block.kind != kind {
             continue;
         }
         if logic.is_point_in_rectangle(
@@ -349,15 +605,14 @@ fn get_specific_block_id_under_point<L: Physics>(
     }
     None
 }
-/// Add caching to this!!!!
-/// The cache needs to be reset every frame but it'd definitely be worth it
+/// Looked up in the index the block list keeps up to date, no need to walk it
 #[inline]
-pub fn index_by_block_id(id: &ID, blocks: &[Block]) -> Option<usize> {
-    (0..blocks.len()).find(|&block_id| blocks[block_id].id == *id)
+pub fn index_by_block_id(id: &ID, blocks: &BlockList) -> Option<usize> {
+    blocks.index_of(id)
 }
 
 fn is_there_a_loop_in_block_connections_for_block(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     block_index: ID,
 ) -> bool {
     let mut already_checked = Vec::new();
@@ -369,7 +624,7 @@ fn is_there_a_loop_in_block_connections_for_block(
 }
 
 fn is_there_a_loop_in_block_connections_for_block_internal(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     block_id: ID,
     already_checked: &mut Vec<ID>,
 ) -> bool {
@@ -378,7 +633,10 @@ fn is_there_a_loop_in_block_connections_for_block_internal(
         return true;
     }
     already_checked.push(block_id);
-    let block = &blocks[index_by_block_id(&block_id, blocks).unwrap()];
+    let Some(idx) = index_by_block_id(&block_id, blocks) else {
+        return false;
+    };
+    let block = &blocks[idx];
     if let Some(connected_below) = block.connected_below.get(){
         return is_there_a_loop_in_block_connections_for_block_internal(
             blocks,
@@ -402,20 +660,18 @@ fn custom_join<T: std::string::ToString>(vector: &Vec<T>, separator: &str) -> St
 }
 
 #[inline]
-fn get_ids_connected_below_block(
+pub fn get_ids_connected_below_block(
     top_most_block_id: ID,
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     found: &mut Vec<ID>,
 ) -> Vec<ID> {
-    let block = &blocks[index_by_block_id(&top_most_block_id, blocks).unwrap()];
-
+    // Links to missing blocks and loops end the stack early, WorkSpace::repair removes them
+    let Some(idx) = index_by_block_id(&top_most_block_id, blocks) else {
+        return found.to_vec();
+    };
+    let block = &blocks[idx];
     if found.contains(&block.id) {
-        panic!(
-            "Infinite loop found with these ids {} with current {} (len({}))",
-            custom_join(&found.to_vec(), ", "),
-            top_most_block_id,
-            found.len()
-        )
+        return found.to_vec();
     }
     found.push(top_most_block_id);
     if let Some(connected_below) = block.connected_below.get() {
@@ -437,7 +693,7 @@ fn get_ids_connected_below_block(
 }
 #[inline]
 fn index_by_block_ids(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     ids: &Vec<ID>,
 ) -> Vec<Option<usize>> {
     let mut return_list = Vec::new();
@@ -447,13 +703,13 @@ fn index_by_block_ids(
     return_list
 }
 fn get_total_height_of_blocks(
-    blocks: &Vec<Block>,
+    blocks: &BlockList,
     indexes: Vec<usize>,
 ) -> SizeType {
     let mut height = 0.0;
     for idx in indexes {
         let block = &blocks[idx];
-        height += block.height.get();
+        height += block.full_height();
     }
     height
 }
@@ -467,7 +723,7 @@ fn get_object_out_of_option_list<T>(list: Vec<Option<T>>, skip_empty:bool) -> Ve
     new
 }
 
-fn move_block_to_connected(blocks: &mut Vec<Block>, index: &Option<usize>) {
+fn move_block_to_connected(blocks: &mut BlockList, index: &Option<usize>) {
     if index.is_none() {
         return;
     }
@@ -487,53 +743,14 @@ fn move_block_to_connected(blocks: &mut Vec<Block>, index: &Option<usize>) {
     );
     
     
-    let own_block_id_index = block_query
+    let Some(own_block_id_index) = block_query
         .iter()
         .position(|&id| id == block.id)
-        .unwrap_or_else(|| 
-            {
-
-                let mut final_string = String::new();
-                let mut block_names = Vec::new();
-                for entry in block_query.iter(){
-                    let idx = index_by_block_id(entry, blocks).unwrap();
-                    let block = &blocks[idx];
-                    let block_name = custom_join(&block.name, "{}");
-                    block_names.push((block.id, block_name));
-
-                }
-                let mut max = 0;
-                for i in block_query.iter(){
-                    let num = usize::from(*i);
-                    if num > max{
-                        max = num;
-                    }
-                }
-                let padding = max.ilog10();
-                let mut padding_string = String::new();
-                for _ in 0..padding{
-                    padding_string+=" ";
-                }
-                for  name in block_names.iter(){
-                    final_string+=&format!("ID({}{}) -> {}\n",padding_string,usize::from(name.0),name.1);
-                }
-                final_string+=&format!("\nNot found: ID({}) -> {}", usize::from(block.id), custom_join(&blocks[index_by_block_id(&block.id, blocks).unwrap()].name, "{}"));
-                
-                let mut infos = Vec::new();
-                infos.push("A block thought it was inside a structure yet the structure wasn't connected to the block anymore - KEEP THE BLOCKS UPDATED".to_string());
-                infos.push(format!("Structure:\n{}", final_string));
-                infos.push(format!("\nBlock data: {:#?}", block));
-                if block.connected_top == block.connected_above{
-                    infos.push(format!("\nTopmost/Above Block data: {:#?}", top_block));
-                }else{
-                    infos.push(format!("\nTopmost Block data: {:#?}", top_block));
-                    if let Some(connected_above) = block.connected_above.get()
-                    {infos.push(format!("\nAbove Block data: {:#?}", blocks[index_by_block_id(&connected_above, blocks).unwrap()]));}
-
-                }
-                println!("{}", custom_join(&infos, "\n"));
-                std::process::exit(0);});
-                
+    else {
+        // It thinks it is in a stack that isn't connected to it anymore - WorkSpace::repair fixes the top, until then it stays where it is
+        block.recently_moved.set(false);
+        return;
+    };
 
     let blocks_to_offset_with =
         &block_query[0..own_block_id_index + 1].to_vec();
@@ -545,8 +762,9 @@ fn move_block_to_connected(blocks: &mut Vec<Block>, index: &Option<usize>) {
     );
 
     if block.recently_moved.get() {
-        block.x.set(top_block.x.get());
-        block.y.set(
+        blocks.set_position(
+            block,
+            top_block.x.get(),
             total_offset as CoordinateType + top_block.y.get() - block.height.get() as CoordinateType,
         );
         block.recently_moved.set(false);
@@ -573,6 +791,7 @@ fn  map_bool_to_sign<T:num_traits::One+ std::ops::Neg<Output = T>>(bool:bool)->T
 
 fn handle_mouse_wheel<F: ExtendedFramework<f64>>(
     camera: &mut Camera,
+    mouse_pos: (isize, isize),
     framework: &F,
     scroll_multiplier: f64,
     extra_scroll_multiplier: f64,
@@ -597,20 +816,22 @@ fn handle_mouse_wheel<F: ExtendedFramework<f64>>(
 
             
         if is_key_bind_down("modify_camera_z", framework) {
-            camera.z -= mouse_wheel_temp.1 * mul;
+            // Zooms towards what is under the mouse
+            camera.zoom_at(ZOOM_STEP.powf(mouse_wheel_temp.1), mouse_pos);
         } else {
+            // The same distance on the screen at any zoom
             camera.x -= -((mouse_wheel_temp.0
-                * mul) as isize*map_bool_to_sign::<isize>(invert_mouse_x));
+                * mul / camera.zoom()) as isize*map_bool_to_sign::<isize>(invert_mouse_x));
             camera.y -= -((mouse_wheel_temp.1
-                * mul) as isize*map_bool_to_sign::<isize>(invert_mouse_y));
+                * mul / camera.zoom()) as isize*map_bool_to_sign::<isize>(invert_mouse_y));
         }
     }
 }
 fn reorder_blocks(
-    blocks: &mut Vec<Block>,
+    blocks: &mut BlockList,
     selected: &mut usize,
 ) {
-            reorder_element(blocks, *selected, 0);
+            blocks.move_to(*selected, 0);
             *selected = 0;
         }
 fn handle_mouse_or_get_selected<
@@ -619,25 +840,26 @@ fn handle_mouse_or_get_selected<
 >(
     mouse_down: bool,
     last_mouse_down: bool,
-    blocks: &mut Vec<Block>,
-    camera: &mut Camera,
+    workspace: &mut WorkSpace<L>,
     mouse_pos: (isize, isize),
     // mouse_delta: (f32, f32),
     framework: &F,
     // scroll_multiplier: f32,
     selected: Option<usize>,
-    logic: &L,
-    _selected_type_is_action: bool,
+    _selected_kind: BlockKind,
     cursor_style: &mut Option<mirl::platform::Cursor>,
     cursors: &mut mirl::platform::mouse::Cursors,
+    output_color_names: &[String],
+    font: &Font,
 ) -> Option<usize> {
     // There are too many problems with dealing with null when the mouse is outside the window, so instead we just check if the mouse is with in the window :)
     let mut selected = selected;
+    let (pos_x, pos_y) = workspace.camera.to_world(mouse_pos);
     let under_mouse = get_block_id_under_point(
-        blocks,
-        mouse_pos.0 + camera.x,
-        mouse_pos.1 + camera.y,
-        logic,
+        &workspace.blocks,
+        pos_x,
+        pos_y,
+        workspace.logic,
     );
     if under_mouse.is_some() {
         if mouse_down {
@@ -649,6 +871,20 @@ fn handle_mouse_or_get_selected<
         *cursor_style = Some(cursors.default)
     }
 
+    // If ctrl is pressed, the structure below should be connected with the structure above
+    let disconnect_single = is_key_bind_down("select_single", framework);
+    let duplicate = is_key_bind_down("duplicate", framework);
+    if mouse_down && !last_mouse_down && duplicate {
+        // The copy is what gets dragged, the original stays where it is
+        if let Some(grabbed) = under_mouse {
+            match workspace.duplicate_structure(grabbed, disconnect_single, output_color_names, font) {
+                Ok(copy) => return Some(copy),
+                Err(error) => framework.log(&format!("Unable to duplicate: {}", error)),
+            }
+        }
+    }
+    let blocks = &workspace.blocks;
+
     if mouse_down {
         // No clue what this is about but it works so I'm not gonna change it
         if !last_mouse_down {
@@ -659,13 +895,16 @@ fn handle_mouse_or_get_selected<
             // When this block is selected, disconnect it from the blocks above
             let selected_block = &blocks[selected];
             
-            // If ctrl is pressed, the structure below should be connected with the structure above
-            let disconnect_single = is_key_bind_down("select_single", framework);
-            let duplicate =is_key_bind_down("duplicate", framework);
-            if disconnect_single&&duplicate{
-                // Only copy the singular block
-            }else if duplicate{
-                // Copy entire block structure downwards
+            if duplicate{
+                // Copies start out disconnected, nothing to do while dragging them
+            }
+            else if selected_block.stored_in.get().is_some(){
+                // Dragged out of an input
+                selected_block.detach_from_input(blocks, font);
+            }
+            else if selected_block.in_slot.get().is_some(){
+                // Dragged out of a container
+                selected_block.detach_from_slot(blocks, disconnect_single);
             }
             else if disconnect_single{
                 selected_block.connect_below_to_above(blocks);
@@ -678,7 +917,14 @@ fn handle_mouse_or_get_selected<
         // Connect block previously selected if possible
         if let Some(selected)=selected {
             let block = &blocks[selected];
-            if block.block_type == 1 {
+            if !block.kind.is_stackable() {
+                if let Some((host_id, input_idx)) = block.possible_input.take() {
+                    block.attach_to_input(host_id, input_idx, blocks, font);
+                }
+                return None;
+            }
+            if let Some((container_id, slot)) = block.possible_slot.take() {
+                block.attach_to_slot(container_id, slot, blocks);
                 return None;
             }
             if block.possible_connection_above.get().is_some() {
@@ -697,7 +943,10 @@ fn handle_mouse_or_get_selected<
                     ).get_id_of_idx(blocks),
                     &mut Vec::from([blocks[selected].id]),
                 ) {
-                    panic!("Loop detected in block structure");
+                    // The stack would end up below itself, it stays where it was let go instead
+                    debug("Refused a connection that would form a loop", framework);
+                    block.possible_connection_above.set(None);
+                    return None;
                 }
                 block.connect_to_possibly_above(blocks);
                 
@@ -743,189 +992,77 @@ fn handle_mouse_or_get_selected<
 }
 
 
-fn load_blocks<F: FileSystem, L: Physics>(
-    file_system: &F,
-    block_output_types: &mut Vec<String>,
-    block_output_colors: &mut Vec<u32>,
+/// Starts, stops or moves the caret of typing into an input, or opens, closes or picks from a dropdown, on the frame the mouse went down
+///
+/// Returns whether the click was used for that, so it doesn't also grab a block or move the camera
+fn handle_input_click<L: Physics, F: ExtendedFramework<f64>>(
+    literal_editor: &mut Option<LiteralEditor>,
+    dropdown: &mut Option<Dropdown>,
+    workspace: &WorkSpace<L>,
+    mouse_pos: (isize, isize),
+    framework: &F,
     font: &Font,
-    workspace: &mut WorkSpace<L>,
-) -> (Vec<Block>, Vec<Block>, HashMap<String, String>) {
-    let path = r"C:\personal\games\minecraft\Automated\ender_py\procedures";
-    let mut action_blocks = Vec::new();
-    let mut inline_blocks = Vec::new();
-    let fallback_language = "en";
-    let mut translation = HashMap::new();
-    let all_plugin_folders = file_system.get_folders_in_folder(path);
-    
-    
-    let mut errors: Vec<String> = Vec::new();
-    for plugin_folder in all_plugin_folders {
-        let plugin_path = file_system.join(path, &plugin_folder);
-        let setting_file = file_system.join(&plugin_path, "settings.json");
-        let translation_file =
-            file_system.join(&plugin_path, "translation.csv");
-        
-        
-        let translation_data =
-            file_system.get_file_contents(&translation_file).unwrap();
-        let (_used_language, parsed_translation) =
-            crate::internal::svg::parse_translations(&translation_data.as_string().unwrap(), fallback_language,fallback_language)
-                .unwrap();
-        translation = parsed_translation;
-
-
-        let settings_json: serde_json::Value = serde_json::from_str(
-            &file_system
-                .get_file_contents(&setting_file)
-                .unwrap()
-                .as_string()
-                .unwrap(),
-        )
-        .expect("Unable to load json settings file");
-        let pre_blocks = settings_json
-            .get("blocks")
-            .ok_or("Error")
-            .expect("Error unwrapping");
-        for block in
-            pre_blocks.as_array().ok_or("Error").expect("Error unwrapping")
+) -> bool {
+    let (pos_x, pos_y) = workspace.camera.to_world(mouse_pos);
+    let clicked = get_block_id_under_point(&workspace.blocks, pos_x, pos_y, workspace.logic)
+        .and_then(|idx| Some((idx, workspace.blocks[idx].get_empty_input_at(pos_x)?)));
+
+    if let Some(open) = dropdown.take() {
+        // The list is drawn above the blocks, so it gets the click first
+        if let (Some((block, input)), Some(layout)) =
+            (open.input(&workspace.blocks), open.layout(&workspace.blocks, font))
         {
-            // framework.log(
-            //     "{}",
-            //     block.get("name").ok_or("Error").expect("Error unwrapping")
-            // );
-            let block_type =
-                block.get("type").ok_or("Error").expect("Error unwrapping");
-            if block_type == "action" || block_type == "inline" {
-                let internal_name: String = block
-                    .get("name")
-                    .expect("Missing name")
-                    .as_str()
-                    .expect("Error unwrapping")
-                    .to_string();
-
-                let output;
-                if let Some(_output) = block.get("output") {
-                    if let Some(__output) = _output.as_str() {
-                        output = __output.to_string()
-                    } else {
-                        errors.push(format!(
-                            "Output of {} isn't a string",
-                            internal_name
-                        ));
-                        output = "You aren't supposed to read me".into();
-                    }
-                } else {
-                    errors
-                        .push(format!("Missing output for {}", internal_name));
-                    output = "You aren't supposed to read me either".into();
-                }
-
-                if !block_output_types.contains(&output) {
-                    block_output_types.push(output.clone());
-                    block_output_colors.push(generate_random_color());
-                }
-                let name: String;
-                if let Some(_name) = translation.get(&internal_name) {
-                    name = (*_name).clone()
-                } else {
-                    errors.push(format!(
-                        "Translation key '{}' not found",
-                        internal_name
-                    ));
-                    name = internal_name.clone()
-                }
-
-                let _empty = serde_json::Value::Array(Vec::new());
-                let pre_inputs = block
-                    .get("inputs")
-                    .unwrap_or(&_empty)
-                    .as_array()
-                    .expect("Error unwrapping");
-
-                let mut inputs = Vec::new();
-                for pre_input in pre_inputs {
-                    let temp = pre_input.as_object().unwrap();
-                    let key_and_return_temp;
-                    let in_case_of_literal_keys_and_return_values =
-                        temp.get("expected");
-
-                    let mut expected_literal_allowed = Vec::new();
-                    let mut expected_literal_return = Vec::new();
-
-                    if in_case_of_literal_keys_and_return_values.is_some() {
-                        key_and_return_temp =
-                            in_case_of_literal_keys_and_return_values
-                                .expect("THIS SHOULD LITERALLY BE IMPOSSIBLE")
-                                .as_object();
-                        //framework.log("{:?}", key_and_return_temp);
-
-                        let keys = key_and_return_temp.expect("Expected key 'expected' to be a dict, are you sure you didn't accidentally make it a list?").keys();
-                        for key in keys {
-                            let value =
-                                key_and_return_temp.unwrap().get(key).unwrap();
-                            expected_literal_allowed.push(key.clone());
-                            expected_literal_return
-                                .push(value.as_str().unwrap().to_string());
-                        }
-                    }
-
-                    let input = BlockInput::new(
-                        temp.get("type").unwrap().as_str().unwrap().to_string(),
-                        None,
-                        expected_literal_allowed,
-                        expected_literal_return,
-                    )
-                    .unwrap();
-                    inputs.push(input);
-                }
-                //framework.log(">{:?}", pre_inputs);
-
-                let inline = block_type == "inline";
-                let block_type_id = if inline {
-                    1
-                } else {
-                    0
-                };
-                if !errors.is_empty() {
-                    continue;
-                }
-
-                let block = Block::new(
-                    name.clone(),
-                    internal_name,
-                    0,
-                    0,
-                    block_type_id,
-                    Vec::new(),
-                    Vec::new(),
-                    Vec::new(),
-                    output,
-                    inputs,
-                    block_output_types,
-                    font,
-                    workspace,None
-                );
-                if inline {
-                    inline_blocks.push(block);
-                } else {
-                    action_blocks.push(block);
-                }
-            } else {
-                //framework.log("Skipping Block as {} is not supported", block_type);
-                continue;
+            let visible = open.visible_options(input);
+            if let Some(row) = Dropdown::option_at(&layout, visible.len(), pos_x, pos_y) {
+                block.set_literal(open.input_idx, Some(visible[row].0.to_string()), &workspace.blocks, font);
+                return true;
+            }
+            if Dropdown::contains(&layout, visible.len(), pos_x, pos_y) {
+                *dropdown = Some(open);
+                return true;
             }
         }
+        // Clicking its own input again only closes it
+        if clicked.is_some_and(|(idx, input_idx)| {
+            workspace.blocks[idx].id == open.block && input_idx == open.input_idx
+        }) {
+            return true;
+        }
     }
 
-    if !errors.is_empty() {
-        panic!(
-            "Unable to load plugins due to the following error(s):\n{:#?}",
-            errors
-        );
+    if let (Some(editor), Some((idx, input_idx))) = (literal_editor.as_mut(), clicked) {
+        let block = &workspace.blocks[idx];
+        if editor.block == block.id && editor.input_idx == input_idx {
+            // Relative to the block first, world coordinates are too large for exact floats
+            let text_start = block.input_offsets.borrow()[input_idx * 2 + 1] + LITERAL_PADDING;
+            let caret = editor.caret_at(
+                (pos_x - block.x.get() as isize) as SizeType - text_start,
+                (block.height.get() / 2.0) as f32,
+                font,
+            );
+            editor.move_caret(caret, is_key_bind_down("shift", framework));
+            return true;
+        }
     }
-
-    (action_blocks, inline_blocks, translation)
+    // Clicking anywhere else keeps what was typed
+    if let Some(editor) = literal_editor.take() {
+        editor.apply(&workspace.blocks, font);
+    }
+    if is_key_bind_down("duplicate", framework) {
+        return false;
+    }
+    let Some((idx, input_idx)) = clicked else {
+        return false;
+    };
+    let block = &workspace.blocks[idx];
+    if block.inputs[input_idx].is_dropdown() {
+        *dropdown = Some(Dropdown::new(block, input_idx, framework.get_all_keys_down()));
+    } else {
+        *literal_editor = Some(LiteralEditor::new(block, input_idx, framework.get_all_keys_down()));
+    }
+    true
 }
+
 #[inline]
 pub fn generate_random_color() -> u32 {
     getrandom::u32().expect("Unable to generate random color")
@@ -977,10 +1114,11 @@ pub fn main_loop<
     // render_settings: &S,
     // logic: &L,
     buffer: &Buffer,
+    settings: &crate::settings::Settings,
 )-> Result<(), Box<dyn std::error::Error>> {
     debug("Setting Default Key-binds", framework);
-    add_key_bind("single_select", mirl::platform::KeyCode::LeftControl)?;
-    add_key_bind("single_select", mirl::platform::KeyCode::RightControl)?;
+    add_key_bind("select_single", mirl::platform::KeyCode::LeftControl)?;
+    add_key_bind("select_single", mirl::platform::KeyCode::RightControl)?;
     add_key_bind("duplicate", mirl::platform::KeyCode::LeftAlt)?;
     add_key_bind("duplicate", mirl::platform::KeyCode::RightAlt)?;
     add_key_bind("debug_block", mirl::platform::KeyCode::I)?;
@@ -990,8 +1128,24 @@ pub fn main_loop<
     add_key_bind("scroll_multiply", mirl::platform::KeyCode::RightShift)?;
     add_key_bind("modify_camera_z", mirl::platform::KeyCode::LeftControl)?;
     add_key_bind("modify_camera_z", mirl::platform::KeyCode::RightControl)?;
+    add_key_bind("control", mirl::platform::KeyCode::LeftControl)?;
+    add_key_bind("control", mirl::platform::KeyCode::RightControl)?;
+    add_key_bind("save_workspace", mirl::platform::KeyCode::S)?;
+    add_key_bind("generate_code", mirl::platform::KeyCode::G)?;
+    add_key_bind("dismiss_messages", mirl::platform::KeyCode::Escape)?;
+    add_key_bind("switch_language", mirl::platform::KeyCode::L)?;
+    add_key_bind("shift", mirl::platform::KeyCode::LeftShift)?;
+    add_key_bind("shift", mirl::platform::KeyCode::RightShift)?;
+    add_key_bind("undo", mirl::platform::KeyCode::Z)?;
+    add_key_bind("delete", mirl::platform::KeyCode::Delete)?;
+    add_key_bind("delete", mirl::platform::KeyCode::Backspace)?;
     
     
+    let plugin_paths = crate::settings::PluginPaths::check(&settings.plugin_paths)?;
+    for missing in &plugin_paths.missing {
+        framework.log(&format!("Plugin folder '{}' does not exist, skipping it", missing));
+    }
+
     debug("Setting up physics and workspace list", framework);
     let fast_physics = crate::logic::LogicFast::new();
     let accurate_physics = crate::logic::LogicFast::new();
@@ -1016,7 +1170,7 @@ pub fn main_loop<
     debug("Setting default variables", framework);
     // Settings - Do touch!
     // Settings - Modifiable after launch
-    let snap_distance = 70.0;
+    let snap_distance = SNAP_DISTANCE;
     let scroll_multiplier = 5.0;
     let extra_scroll_multiplier = 5.0;
     let max_fps = 60;
@@ -1049,12 +1203,23 @@ pub fn main_loop<
     let mut fps_list: Vec<u64> = Vec::new();
 
     let mut selected: Option<usize> = None;
-    let mut selected_type_is_action: bool = false;
+    let mut selected_kind = BlockKind::Inline;
 
 
     let mut next_workspace_key_down = false;
     let mut previous_workspace_key_down = false;
 
+    // The input being typed into, keys go to it instead of the key binds without ctrl
+    let mut literal_editor: Option<LiteralEditor> = None;
+    // mirl has no access to the system clipboard
+    let mut clipboard = String::new();
+    // The open option list of a dropdown input, keys go to it like to the literal editor
+    let mut dropdown: Option<Dropdown> = None;
+    // The mouse went down on an input, ignored until let go
+    let mut click_used_by_literal = false;
+    // Lists the loaded blocks, shared by every workspace
+    let mut palette = Palette::default();
+
     debug("Loading custom cursors", framework);
     let cursors = framework.load_custom_cursor(
         U2::new(0),
@@ -1066,15 +1231,50 @@ pub fn main_loop<
         file_system.get_file_contents("inter.ttf").unwrap().as_font().unwrap();
 
 
-    let (action_blocks, inline_blocks, _translation) = load_blocks(
+    let mut loaded_plugins = crate::plugin::load_blocks(
         file_system,
         &mut block_output_color_names,
         &mut block_output_color_rgb,
         &font,
         current_workspace,
+        &plugin_paths.existing,
+        &settings.language,
     );
-    current_workspace.blocks.extend(action_blocks.iter().cloned());
-    current_workspace.blocks.extend(inline_blocks.iter().cloned());
+    // Shown until dismissed
+    let mut messages = loaded_plugins.error_messages();
+    for message in &messages {
+        framework.log(message);
+    }
+    // Opened procedure files already contain the blocks that were lying around when saving
+    let mut save_path = settings.open_file.clone();
+    if let Some(open_file) = &settings.open_file {
+        debug("Loading procedure file", framework);
+        match current_workspace.load(
+            file_system,
+            open_file,
+            &loaded_plugins.definitions(),
+            &block_output_color_names,
+            &font,
+            &loaded_plugins.types,
+        ) {
+            Ok(report) => {
+                if !report.is_clean() {
+                    framework.log(&format!("Loaded '{}' with problems:\n{}", open_file, report));
+                    messages.extend(report.to_string().lines().map(String::from));
+                }
+            }
+            Err(error) => {
+                // Starting empty is more useful than not starting, but saving must not overwrite the unreadable file
+                let message = format!("Unable to load '{}': {}", open_file, error);
+                framework.log(&message);
+                messages.push(message);
+                save_path = None;
+            }
+        }
+    }
+    let save_path = save_path.unwrap_or_else(|| {
+        crate::settings::absolute_path(&format!("untitled{}", crate::internal::save::PROCEDURE_FILE_EXTENSION))
+    });
 
     frame_start = framework.get_time();
 
@@ -1084,9 +1284,53 @@ pub fn main_loop<
 
         let mut cursor_style: Option<mirl::platform::Cursor> = None;
 
+        let editing = literal_editor.is_some() || dropdown.is_some();
+        if let Some(editor) = &mut literal_editor {
+            debug("Typing into input", framework);
+            let outcome = editor.handle_keys(
+                framework.get_all_keys_down(),
+                is_key_bind_down("shift", framework),
+                is_key_bind_down("control", framework),
+                &mut clipboard,
+            );
+            match outcome {
+                EditOutcome::Editing => editor.apply(&current_workspace.blocks, &font),
+                EditOutcome::Done => {
+                    editor.apply(&current_workspace.blocks, &font);
+                    literal_editor = None;
+                }
+                EditOutcome::Cancelled => {
+                    editor.revert(&current_workspace.blocks, &font);
+                    literal_editor = None;
+                }
+            }
+        }
+        if let Some(open) = &mut dropdown {
+            debug("Navigating dropdown", framework);
+            let outcome = match open.input(&current_workspace.blocks) {
+                Some((block, input)) => {
+                    let outcome = open.handle_keys(
+                        framework.get_all_keys_down(),
+                        is_key_bind_down("shift", framework),
+                        is_key_bind_down("control", framework),
+                        input,
+                    );
+                    if let DropdownOutcome::Chosen(chosen) = &outcome {
+                        block.set_literal(open.input_idx, Some(chosen.clone()), &current_workspace.blocks, &font);
+                    }
+                    outcome
+                }
+                None => DropdownOutcome::Closed,
+            };
+            if outcome != DropdownOutcome::Open {
+                dropdown = None;
+            }
+        }
+
         debug("Checking for change in workspace", framework);
         let mut reload_workspace = false;
         if is_key_bind_down("next_workspace", framework)
+            && !editing
             && !next_workspace_key_down
             && workspace_length > current_workspace_id + 1
         {
@@ -1097,6 +1341,7 @@ pub fn main_loop<
             next_workspace_key_down = false
         }
         if is_key_bind_down("previous_workspace", framework)
+            && !editing
             && !previous_workspace_key_down
             && current_workspace_id > 0
         {
@@ -1108,9 +1353,111 @@ pub fn main_loop<
         }
         if reload_workspace {
             current_workspace = &mut workspaces[current_workspace_id];
-            if current_workspace.blocks.is_empty() {
-                current_workspace.blocks.extend(action_blocks.iter().cloned());
-                current_workspace.blocks.extend(inline_blocks.iter().cloned())
+        }
+
+        if was_key_bind_pressed("switch_language", framework)
+            && is_key_bind_down("control", framework)
+        {
+            let languages = loaded_plugins.translations.languages();
+            if !languages.is_empty() {
+                let next = languages
+                    .iter()
+                    .position(|x| x == loaded_plugins.translations.language())
+                    .map_or(0, |x| (x + 1) % languages.len());
+                debug("Switching language", framework);
+                // Every workspace, not just the shown one, so switching workspaces doesn't show stale names
+                let mut errors = loaded_plugins.set_language(&languages[next], &font);
+                for workspace in workspaces.iter_mut() {
+                    errors.extend(crate::plugin::translate_blocks(&loaded_plugins.translations, &mut workspace.blocks, &font));
+                }
+                current_workspace = &mut workspaces[current_workspace_id];
+                framework.log(&format!("Switched language to '{}'", languages[next]));
+                for error in errors {
+                    framework.log(&error.to_string());
+                    messages.push(error.to_string());
+                }
+            }
+        }
+
+        if was_key_bind_pressed("save_workspace", framework)
+            && is_key_bind_down("control", framework)
+        {
+            debug("Saving workspace", framework);
+            match current_workspace.save(file_system, &save_path) {
+                Ok(()) => framework.log(&format!("Saved to '{}'", save_path)),
+                Err(error) => framework.log(&format!("Unable to save to '{}': {}", save_path, error)),
+            }
+        }
+
+        if was_key_bind_pressed("generate_code", framework)
+            && is_key_bind_down("control", framework)
+        {
+            debug("Generating code", framework);
+            let code_path = crate::generate::code_path_for(&save_path);
+            let written = crate::generate::generate_code(&current_workspace.blocks)
+                .and_then(|code| {
+                    let json = code.to_json().map_err(|x| x.to_string())?;
+                    file_system.write_to_file(&code_path, json.as_bytes()).map_err(|x| x.to_string())?;
+                    Ok(code.orphans)
+                });
+            match written {
+                Ok(orphans) => {
+                    framework.log(&format!("Generated code into '{}'", code_path));
+                    for orphan in orphans {
+                        framework.log(&orphan.to_string());
+                        messages.push(orphan.to_string());
+                    }
+                }
+                Err(error) => framework.log(&format!("Unable to generate code: {}", error)),
+            }
+        }
+
+        if was_key_bind_pressed("undo", framework)
+            && is_key_bind_down("control", framework)
+            && !editing
+            && !mouse_down
+        {
+            // Ctrl+Shift+Z redoes
+            let redo = is_key_bind_down("shift", framework);
+            debug(if redo { "Redoing" } else { "Undoing" }, framework);
+            let done = if redo {
+                current_workspace.redo(&font)
+            } else {
+                current_workspace.undo(&font)
+            };
+            if done {
+                // Steps keep the blocks named in the language they were recorded in - Failed renames were already reported when switching
+                crate::plugin::translate_blocks(&loaded_plugins.translations, &mut current_workspace.blocks, &font);
+            } else {
+                framework.log(if redo { "Nothing to redo" } else { "Nothing to undo" });
+            }
+        }
+        if super::REPAIR_EVERY_FRAME && cfg!(debug_assertions) {
+            for issue in current_workspace.repair(&font) {
+                framework.log(&format!("Repaired: {}", issue));
+            }
+        }
+        // Typing, undoing and last frame's drop moved or resized blocks
+        current_workspace.blocks.refresh_spatial_index();
+
+        if was_key_bind_pressed("delete", framework)
+            && !editing
+            && !mouse_down
+            && !palette.contains(mouse_pos)
+        {
+            let (pos_x, pos_y) = current_workspace.camera.to_world(mouse_pos);
+            let under_mouse = get_block_id_under_point(
+                &current_workspace.blocks,
+                pos_x,
+                pos_y,
+                current_workspace.logic,
+            );
+            if let Some(idx) = under_mouse {
+                // Takes what dragging it would take
+                debug("Deleting block", framework);
+                current_workspace.begin_edit();
+                current_workspace.delete_structure(idx, is_key_bind_down("select_single", framework), &font);
+                current_workspace.end_edit();
             }
         }
 
@@ -1157,33 +1504,104 @@ pub fn main_loop<
         mouse_down = framework.is_mouse_down(mirl::platform::MouseButton::Left);
         mouse_held = mouse_down_temp && mouse_down;
 
-        handle_mouse_wheel(
-            &mut current_workspace.camera,
-            framework,
-            scroll_multiplier,extra_scroll_multiplier,invert_mouse_wheel_x,invert_mouse_wheel_y,horizontal_mouse_wheel_by_default
+        let over_palette = palette.contains(mouse_pos);
+        if over_palette {
+            if let Some(mouse_wheel) = framework.get_mouse_scroll() {
+                let rows = palette.layout(loaded_plugins.iter_definitions());
+                palette.scroll_by((mouse_wheel.1 * scroll_multiplier) as isize, &rows, buffer.height as isize);
+            }
+        } else {
+            let camera_before = current_workspace.camera.clone();
+            handle_mouse_wheel(
+                &mut current_workspace.camera,
+                mouse_pos,
+                framework,
+                scroll_multiplier,extra_scroll_multiplier,invert_mouse_wheel_x,invert_mouse_wheel_y,horizontal_mouse_wheel_by_default
+            );
+            current_workspace.record_camera_move(camera_before);
+        }
+
+        // A block dragged out of the palette is already selected
+        let mut spawned = false;
+        if mouse_down && !mouse_down_temp && !mouse_outside {
+            // Everything until letting go (or until the opened input is done) is one undo step
+            current_workspace.begin_edit();
+            if over_palette {
+                // Nothing behind the palette can be clicked through it
+                click_used_by_literal = true;
+                if let Some(editor) = literal_editor.take() {
+                    editor.apply(&current_workspace.blocks, &font);
+                }
+                dropdown = None;
+                let rows = palette.layout(loaded_plugins.iter_definitions());
+                if let Some((definition, offset)) = Palette::block_at(&rows, mouse_pos) {
+                    debug("Spawning block from palette", framework);
+                    match definition.duplicate(&block_output_color_names, &font, current_workspace) {
+                        Ok(copy) => {
+                            // The offset is in screen pixels, the world position of the block isn't
+                            let camera = &current_workspace.camera;
+                            let (pos_x, pos_y) = camera.to_world(mouse_pos);
+                            copy.x.set((pos_x - camera.unscale(offset.0)) as CoordinateType);
+                            copy.y.set((pos_y - camera.unscale(offset.1)) as CoordinateType);
+                            current_workspace.blocks.push(copy);
+                            selected = Some(current_workspace.blocks.len() - 1);
+                            click_used_by_literal = false;
+                            spawned = true;
+                        }
+                        Err(error) => framework.log(&format!("Unable to spawn block: {}", error)),
+                    }
+                }
+            } else {
+                click_used_by_literal = handle_input_click(
+                    &mut literal_editor,
+                    &mut dropdown,
+                    current_workspace,
+                    mouse_pos,
+                    framework,
+                    &font,
+                );
+            }
+        } else if !mouse_down {
+            click_used_by_literal = false;
+        }
+
+        let (trash_x, trash_y, trash_width, trash_height) = trash_area(buffer);
+        let over_trash = current_workspace.logic.is_point_in_rectangle(
+            mouse_pos.0,
+            mouse_pos.1,
+            trash_x,
+            trash_y,
+            trash_width,
+            trash_height,
         );
+        if !mouse_down && mouse_down_temp && (over_trash || over_palette) {
+            // Dropped into the trash (or back onto the palette) instead of connecting it anywhere
+            if let Some(idx) = selected.take() {
+                debug("Deleting dropped block", framework);
+                current_workspace.delete_structure(idx, false, &font);
+            }
+        }
 
-        if !mouse_outside {
+        if !mouse_outside && !click_used_by_literal && !spawned {
             debug("Getting new selected", framework);
             selected = handle_mouse_or_get_selected(
                 mouse_down,
                 mouse_held,
-                &mut current_workspace.blocks,
-                &mut current_workspace.camera,
+                current_workspace,
                 mouse_pos,
                 // mouse_delta,
                 framework,
                 // scroll_multiplier,
                 selected,
-                current_workspace.logic,
-                selected_type_is_action,
+                selected_kind,
                 &mut cursor_style,
                 &mut cursors.clone(),
+                &block_output_color_names,
+                &font,
             );
         }
-        if selected.is_some() && !mouse_held {
-            selected_type_is_action =
-                current_workspace.blocks[selected.unwrap()].block_type == 0;
+        if let Some(selected) = selected.filter(|_| !mouse_held) {
+            selected_kind = current_workspace.blocks[selected].kind;
         }
 
         if mouse_down {
@@ -1199,25 +1617,32 @@ pub fn main_loop<
         }
 
 
-        //framework.log("Selected: {:?}, {}", selected, selected_type_is_action);
+        //framework.log("Selected: {:?}, {}", selected, selected_kind);
         debug("Reordering block structure", framework);
+        // What the mouse moved on the screen, in the world - Rounded like clicks are, so dragged blocks stay under the mouse at any zoom
+        let world_delta = {
+            let now = current_workspace.camera.to_world(mouse_pos);
+            let before = current_workspace.camera.to_world(mouse_pos.sub(mouse_delta));
+            (now.0 - before.0, now.1 - before.1)
+        };
         
-    if mouse_down {
+    if mouse_down && !click_used_by_literal {
         if let Some(idx) = &mut selected {
         reorder_blocks(
             &mut current_workspace.blocks,
             idx,
         );
-        let selected_block = &mut current_workspace.blocks[0];
+        let selected_block = &current_workspace.blocks[0];
     
-            selected_block.x
-                .set((selected_block.x.get() as isize + mouse_delta.0) as CoordinateType);
-            selected_block.y
-                .set((selected_block.y.get() as isize + mouse_delta.1) as CoordinateType);
+            current_workspace.blocks.set_position(
+                selected_block,
+                (selected_block.x.get() as isize + world_delta.0) as CoordinateType,
+                (selected_block.y.get() as isize + world_delta.1) as CoordinateType,
+            );
     
     } else {
-            current_workspace.camera.x -= mouse_delta.0;
-            current_workspace.camera.y -= mouse_delta.1;
+            current_workspace.camera.x -= world_delta.0;
+            current_workspace.camera.y -= world_delta.1;
         }
     }
 
@@ -1235,6 +1660,12 @@ pub fn main_loop<
             &font,
             current_workspace.logic,
         );
+        if let Some(editor) = &literal_editor {
+            render_literal_editor(buffer, editor, &current_workspace.blocks, &current_workspace.camera, &font);
+        }
+        if let Some(open) = &dropdown {
+            render_dropdown(buffer, open, &current_workspace.blocks, &current_workspace.camera, &font);
+        }
         // handle_and_render_action_blocks_on_screen(
         //     buffer,
         //     &current_workspace.camera,
@@ -1250,14 +1681,28 @@ pub fn main_loop<
             &current_workspace.blocks,
             &font,
             &selected,
-            snap_distance,
+            // Stays the same distance on the screen
+            snap_distance / current_workspace.camera.zoom() as SizeType,
             current_workspace.logic,
             &block_output_color_rgb,
-            selected_type_is_action, framework
+            selected_kind, framework,
+            &loaded_plugins.types,
         );
+        let rows = palette.layout(loaded_plugins.iter_definitions());
+        render_palette(buffer, &rows, &block_output_color_rgb, &font, current_workspace.logic);
+        if let Some(selected) = selected.filter(|_| mouse_down) {
+            // Above the palette, it can be dragged over it
+            render_dragged_stack(buffer, &current_workspace.camera, &current_workspace.blocks, selected, &block_output_color_rgb, &font, current_workspace.logic);
+            render_trash(buffer, over_trash);
+        }
+        // After the layout so the step holds where the blocks ended up
+        if !mouse_down && literal_editor.is_none() && dropdown.is_none() {
+            current_workspace.end_edit();
+        }
 
-        if is_key_bind_down("debug_block", framework){
+        if is_key_bind_down("debug_block", framework) && !editing {
             framework.log("\nINFOS\n");
+            let (pos_x, pos_y) = current_workspace.camera.to_world(mouse_pos);
             if let Some(selected_block_index) = selected{
                 let all_connected = get_ids_connected_below_block(current_workspace.blocks[selected_block_index].id, &current_workspace.blocks, &mut Vec::new());
                 
@@ -1265,7 +1710,7 @@ pub fn main_loop<
                 framework.log(&format!("Selected Block info:\n{:#?}", current_workspace.blocks[index_by_block_id(&id, &current_workspace.blocks).unwrap()]));
 
                 }
-            }else if let Some(under_mouse) = get_block_id_under_point(&current_workspace.blocks, mouse_pos.0, mouse_pos.1, current_workspace.logic){
+            }else if let Some(under_mouse) = get_block_id_under_point(&current_workspace.blocks, pos_x, pos_y, current_workspace.logic){
                 let all_connected = get_ids_connected_below_block(current_workspace.blocks[under_mouse].id, &current_workspace.blocks, &mut Vec::new());
                 
                 for id in all_connected{
@@ -1283,6 +1728,11 @@ pub fn main_loop<
             }
         }
 
+        if was_key_bind_pressed("dismiss_messages", framework) && !editing {
+            messages.clear();
+        }
+        render_messages(buffer, &messages, &font);
+
         //############################################
         debug("Update framework with buffer", framework);
         framework.update(buffer);
@@ -1311,15 +1761,15 @@ pub fn main_loop<
         debug("Updating title", framework);
         framework.set_title(
             &to_monospace_unicode(&format!(
-                "Rust Window {:>4}/{:>5.0} FPS (Sampling {:>3}) | {:>8}x {:>8}y {:>4}z | {:>8}x {:>8}y -> {:>4} {:>4} -> {:>3} {:>3} | {}A + {}I = {}T",
+                "Rust Window {:>4}/{:>5.0} FPS (Sampling {:>3}) | {:>8}x {:>8}y {:>4.2}z | {:>8}x {:>8}y -> {:>4} {:>4} -> {:>3} {:>3} | {}A + {}I = {}T",
                 average_fps,
                 fps,
                 fps_list.len(),
                 current_workspace.camera.x,
                 current_workspace.camera.y,
-                current_workspace.camera.z,
-                current_workspace.camera.x + mouse_pos.0 ,
-                current_workspace.camera.y + mouse_pos.1 ,
+                current_workspace.camera.zoom(),
+                current_workspace.camera.to_world(mouse_pos).0,
+                current_workspace.camera.to_world(mouse_pos).1,
                 mouse_pos.0,
                 mouse_pos.1,
                 mouse_delta.0,
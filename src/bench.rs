@@ -0,0 +1,236 @@
+use std::time::{Duration, Instant};
+
+use fontdue::Font;
+
+use crate::all::{get_block_id_under_point, handle_and_render_action_blocks_on_screen, SNAP_DISTANCE};
+use crate::internal::{Block, BlockKind, WorkSpace};
+use crate::logic::{LogicAccurate, LogicFast, Physics};
+use crate::plugin::LoadedPlugins;
+use crate::CoordinateType;
+use crate::SizeType;
+
+/// Workspace sizes measured when none are given
+pub const DEFAULT_BLOCK_COUNTS: [usize; 3] = [1_000, 10_000, 100_000];
+/// Frames run before measuring, so caches and the spatial index are warm
+const WARMUP_FRAMES: usize = 3;
+const MEASURED_FRAMES: usize = 20;
+/// Blocks per generated stack
+const STACK_SIZE: usize = 10;
+/// Distance between the generated stacks
+const STACK_SPACING: (isize, isize) = (480, 560);
+
+/// Time spent in one part of a frame, summed over the measured frames
+#[derive(Debug, Clone, Default)]
+struct FrameCost {
+    /// Catching the spatial index up with moved blocks
+    index: Duration,
+    /// Moving stacks into place and drawing every visible block
+    render: Duration,
+    /// Hit test under the mouse plus snapping to blocks, inputs and slots
+    queries: Duration,
+}
+
+impl FrameCost {
+    fn total(&self) -> Duration {
+        self.index + self.render + self.queries
+    }
+}
+
+/// `bench [block counts]...` - Fills a workspace with generated stacks and measures what a frame costs, without opening a window
+pub fn bench_headless(
+    settings: &crate::settings::Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use mirl::platform::FileSystem;
+
+    let counts = if settings.positional.is_empty() {
+        DEFAULT_BLOCK_COUNTS.to_vec()
+    } else {
+        settings
+            .positional
+            .iter()
+            .map(|x| x.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| "Usage: bench [block counts]...")?
+    };
+    let plugin_paths = crate::settings::PluginPaths::check(&settings.plugin_paths)?;
+    let file_system =
+        mirl::platform::file_system::NativeFileSystem::new(Vec::from([
+            "inter.ttf",
+        ]))?;
+    let font = file_system.get_file_contents("inter.ttf")?.as_font()?;
+
+    println!(
+        "{:>8} {:>9} {:>10} {:>9} {:>10} {:>10} {:>10}",
+        "blocks", "physics", "frame", "fps", "index", "render", "queries"
+    );
+    for count in counts {
+        for name in ["fast", "accurate"] {
+            let cost = if name == "fast" {
+                bench_workspace(&LogicFast::new(), count, settings, &plugin_paths.existing, &file_system, &font)?
+            } else {
+                bench_workspace(&LogicAccurate::new(), count, settings, &plugin_paths.existing, &file_system, &font)?
+            };
+            let per_frame = |x: Duration| x.as_secs_f64() * 1000.0 / MEASURED_FRAMES as f64;
+            let frame = per_frame(cost.total());
+            println!(
+                "{:>8} {:>9} {:>7.2} ms {:>9.1} {:>7.2} ms {:>7.2} ms {:>7.2} ms",
+                count,
+                name,
+                frame,
+                1000.0 / frame,
+                per_frame(cost.index),
+                per_frame(cost.render),
+                per_frame(cost.queries),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn bench_workspace<L: Physics, D: mirl::platform::FileSystem>(
+    logic: &L,
+    count: usize,
+    settings: &crate::settings::Settings,
+    plugin_paths: &[String],
+    file_system: &D,
+    font: &Font,
+) -> Result<FrameCost, Box<dyn std::error::Error>> {
+    let mut workspace = WorkSpace::new(logic);
+    let mut block_output_color_names = Vec::new();
+    let mut block_output_color_rgb = Vec::new();
+    let loaded_plugins = crate::plugin::load_blocks(
+        file_system,
+        &mut block_output_color_names,
+        &mut block_output_color_rgb,
+        font,
+        &mut workspace,
+        plugin_paths,
+        &settings.language,
+    );
+    fill_workspace(&mut workspace, &loaded_plugins, count, &block_output_color_names, font)?;
+
+    let buffer = mirl::platform::Buffer::new_empty(1000, 600);
+    // Starts in the middle of the generated stacks, then pans across them
+    let origin = (workspace.camera.x, workspace.camera.y);
+    let mut cost = FrameCost::default();
+    for frame in 0..WARMUP_FRAMES + MEASURED_FRAMES {
+        buffer.clear();
+        workspace.camera.x = origin.0 + frame as isize * 7;
+        workspace.camera.y = origin.1 + frame as isize * 5;
+        // The loose block plays the one being dragged, following the camera
+        let dragged = &workspace.blocks[0];
+        workspace.blocks.set_position(
+            dragged,
+            (workspace.camera.x + 500) as CoordinateType,
+            (workspace.camera.y + 300) as CoordinateType,
+        );
+
+        let start = Instant::now();
+        workspace.blocks.refresh_spatial_index();
+        let indexed = Instant::now();
+        handle_and_render_action_blocks_on_screen(
+            &buffer,
+            &workspace.camera,
+            &mut workspace.blocks,
+            &block_output_color_rgb,
+            font,
+            logic,
+        );
+        let rendered = Instant::now();
+        run_queries(&workspace);
+        let queried = Instant::now();
+
+        if frame >= WARMUP_FRAMES {
+            cost.index += indexed - start;
+            cost.render += rendered - indexed;
+            cost.queries += queried - rendered;
+        }
+    }
+    Ok(cost)
+}
+
+/// Everything a frame asks about the blocks around the mouse and the dragged block
+fn run_queries<L: Physics>(workspace: &WorkSpace<L>) {
+    let blocks = &workspace.blocks;
+    let dragged = &blocks[0];
+    let pos = (dragged.x.get() as isize, dragged.y.get() as isize);
+    std::hint::black_box(get_block_id_under_point(blocks, pos.0, pos.1, workspace.logic));
+    std::hint::black_box(workspace.logic.get_block_in_distance(
+        blocks,
+        pos.0 as SizeType,
+        pos.1 as SizeType,
+        SNAP_DISTANCE,
+        Some(0),
+        true,
+    ));
+    std::hint::black_box(workspace.logic.get_slot_in_distance(
+        blocks,
+        pos,
+        SNAP_DISTANCE,
+        &[dragged.id],
+    ));
+    std::hint::black_box(workspace.logic.get_block_input_in_distance(
+        blocks,
+        pos.0 as SizeType,
+        pos.1 as SizeType,
+        SNAP_DISTANCE,
+        &[dragged.id],
+        true,
+        &|_, _| true,
+    ));
+}
+
+/// One loose block followed by stacks of [`STACK_SIZE`] blocks laid out in a square around the camera, each headed by an event when the plugins have one
+fn fill_workspace<L: Physics>(
+    workspace: &mut WorkSpace<L>,
+    loaded_plugins: &LoadedPlugins,
+    count: usize,
+    output_color_names: &[String],
+    font: &Font,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let stackable: Vec<&Block> = loaded_plugins
+        .iter_definitions()
+        .filter(|x| x.kind.can_connect_above())
+        .collect();
+    let loose = *stackable
+        .first()
+        .ok_or("The loaded plugins have no blocks that can be stacked")?;
+    let head = loaded_plugins
+        .iter_definitions()
+        .find(|x| x.kind == BlockKind::Event)
+        .unwrap_or(loose);
+
+    let stacks = count.div_ceil(STACK_SIZE);
+    let columns = (stacks as f64).sqrt().ceil() as usize;
+    let origin = (
+        workspace.camera.x - (columns as isize / 2) * STACK_SPACING.0,
+        workspace.camera.y - (columns as isize / 2) * STACK_SPACING.1,
+    );
+    // Dragged around by every frame, alone so it can snap to the others
+    let loose = loose.duplicate(output_color_names, font, workspace)?;
+    workspace.blocks.push(loose);
+    for idx in 0..count.saturating_sub(1) {
+        let stack = idx / STACK_SIZE;
+        let position = idx % STACK_SIZE;
+        let definition = if position == 0 {
+            head
+        } else {
+            stackable[idx % stackable.len()]
+        };
+        let block = definition.duplicate(output_color_names, font, workspace)?;
+        block.x.set((origin.0 + (stack % columns) as isize * STACK_SPACING.0) as CoordinateType);
+        block.y.set((origin.1 + (stack / columns) as isize * STACK_SPACING.1) as CoordinateType);
+        let above = (position != 0).then(|| workspace.blocks.len() - 1);
+        workspace.blocks.push(block);
+        if let Some(above) = above {
+            let blocks = &workspace.blocks;
+            blocks[blocks.len() - 1].connect_to_block(above, blocks);
+        }
+    }
+    for block in workspace.blocks.iter() {
+        if block.connected_above.get().is_none() {
+            block.recursive_set_topmost(&workspace.blocks, block.id, true);
+        }
+    }
+    Ok(())
+}
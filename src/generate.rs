@@ -0,0 +1,253 @@
+use serde::Serialize;
+
+use crate::all::get_ids_connected_below_block;
+use crate::all::index_by_block_id;
+use crate::internal::Block;
+use crate::internal::BlockList;
+use crate::internal::ID;
+
+/// The json 'code' handed to the python to minecraft-mod pipeline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GeneratedCode {
+    pub procedures: Vec<Procedure>,
+    /// Stacks left out because no event starts them
+    #[serde(skip)]
+    pub orphans: Vec<OrphanStack>,
+}
+
+/// One event block and everything connected below it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Procedure {
+    /// When the statements run
+    pub event: Statement,
+    pub required_imports: Vec<String>,
+    pub required_contexts: Vec<String>,
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Statement {
+    pub block: String,
+    pub inputs: Vec<Value>,
+    /// The stacks in the slots of a container, left out for every other block
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub bodies: Vec<Body>,
+}
+
+/// What one slot of a container runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Body {
+    pub slot: String,
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    /// An inline block stored in the input
+    Block(Statement),
+    /// A typed in value, already mapped through `literal_return`
+    Literal(String),
+    /// Nothing was put into the input -> null
+    Empty,
+}
+
+/// A top-most action block, nothing would ever run it or what is below it
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrphanStack {
+    pub head: ID,
+    pub block: String,
+}
+impl std::fmt::Display for OrphanStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "The stack starting with '{}' (block {}) has no event above it and was left out",
+            self.block, self.head
+        )
+    }
+}
+
+impl GeneratedCode {
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// `something.proc.json` -> `something.code.json`
+pub fn code_path_for(procedure_path: &str) -> String {
+    let stem = procedure_path
+        .strip_suffix(crate::internal::save::PROCEDURE_FILE_EXTENSION)
+        .unwrap_or(procedure_path);
+    format!("{}.code.json", stem)
+}
+
+/// Turns every event stack into a procedure, ordered top to bottom then left to right
+///
+/// Top-most action blocks without an event above them end up in [`GeneratedCode::orphans`]
+pub fn generate_code(blocks: &BlockList) -> Result<GeneratedCode, String> {
+    let mut heads: Vec<&Block> = blocks
+        .iter()
+        .filter(|x| x.is_stack_root())
+        .collect();
+    heads.sort_by_key(|x| (x.y.get(), x.x.get()));
+
+    let mut procedures = Vec::new();
+    let mut orphans = Vec::new();
+    for head in heads {
+        if head.kind.starts_procedure() {
+            procedures.push(generate_procedure(head.id, blocks)?);
+        } else {
+            orphans.push(OrphanStack {
+                head: head.id,
+                block: head.internal_name.clone(),
+            });
+        }
+    }
+    Ok(GeneratedCode { procedures, orphans })
+}
+
+/// `event_block_id` becomes the event, everything below it the statements
+pub fn generate_procedure(
+    event_block_id: ID,
+    blocks: &BlockList,
+) -> Result<Procedure, String> {
+    let mut procedure = Procedure {
+        event: Statement {
+            block: String::new(),
+            inputs: Vec::new(),
+            bodies: Vec::new(),
+        },
+        required_imports: Vec::new(),
+        required_contexts: Vec::new(),
+        statements: Vec::new(),
+    };
+    let stack = get_ids_connected_below_block(event_block_id, blocks, &mut Vec::new());
+    for (position, id) in stack.iter().enumerate() {
+        let statement = generate_statement(id, blocks, &mut procedure)?;
+        if position == 0 {
+            procedure.event = statement;
+        } else {
+            procedure.statements.push(statement);
+        }
+    }
+    Ok(procedure)
+}
+
+fn generate_statement(
+    id: &ID,
+    blocks: &BlockList,
+    procedure: &mut Procedure,
+) -> Result<Statement, String> {
+    let block = &blocks[index_by_block_id(id, blocks)
+        .ok_or_else(|| format!("Block {} is referenced but doesn't exist", id))?];
+    extend_unique(&mut procedure.required_imports, &block.required_imports);
+    extend_unique(&mut procedure.required_contexts, &block.required_contexts);
+
+    let mut inputs = Vec::new();
+    let stored_inputs = block.stored_inputs.borrow();
+    let stored_literals = block.stored_literals.borrow();
+    for (idx, input) in block.inputs.iter().enumerate() {
+        if let Some(stored) = stored_inputs.get(idx).copied().flatten() {
+            inputs.push(Value::Block(generate_statement(&stored, blocks, procedure)?));
+        } else if let Some(literal) = stored_literals.get(idx).cloned().flatten() {
+            inputs.push(Value::Literal(input.resolve_literal(&literal)));
+        } else {
+            inputs.push(Value::Empty);
+        }
+    }
+
+    let mut bodies = Vec::new();
+    for (slot, head) in block.slots.iter().zip(block.stored_slots.borrow().iter()) {
+        let mut statements = Vec::new();
+        if let Some(head) = head {
+            for id in get_ids_connected_below_block(*head, blocks, &mut Vec::new()) {
+                statements.push(generate_statement(&id, blocks, procedure)?);
+            }
+        }
+        bodies.push(Body {
+            slot: slot.name.clone(),
+            statements,
+        });
+    }
+
+    Ok(Statement {
+        block: block.internal_name.clone(),
+        inputs,
+        bodies,
+    })
+}
+
+fn extend_unique(list: &mut Vec<String>, new: &[String]) {
+    for item in new {
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+}
+
+/// `generate <file.proc.json> [output]` - Loads the plugins and the procedure file without opening a window
+pub fn generate_headless(
+    settings: &crate::settings::Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::logic::Physics;
+    use crate::settings::absolute_path;
+    use mirl::platform::FileSystem;
+
+    let args = &settings.positional;
+    let plugin_paths = crate::settings::PluginPaths::check(&settings.plugin_paths)?;
+    for missing in &plugin_paths.missing {
+        eprintln!("Plugin folder '{}' does not exist, skipping it", missing);
+    }
+    let input = args
+        .first()
+        .ok_or("Usage: generate <file.proc.json> [output file]")?;
+    let file_system =
+        mirl::platform::file_system::NativeFileSystem::new(Vec::from([
+            "inter.ttf",
+        ]))?;
+    let font = file_system.get_file_contents("inter.ttf")?.as_font()?;
+    let physics = crate::logic::LogicFast::new();
+    let mut workspace = crate::internal::WorkSpace::new(&physics);
+
+    let mut block_output_color_names = Vec::new();
+    let mut block_output_color_rgb = Vec::new();
+    let loaded_plugins = crate::plugin::load_blocks(
+        &file_system,
+        &mut block_output_color_names,
+        &mut block_output_color_rgb,
+        &font,
+        &mut workspace,
+        &plugin_paths.existing,
+        &settings.language,
+    );
+    for message in loaded_plugins.error_messages() {
+        eprintln!("{}", message);
+    }
+
+    let input = absolute_path(input);
+    let report = workspace.load(
+        &file_system,
+        &input,
+        &loaded_plugins.definitions(),
+        &block_output_color_names,
+        &font,
+        &loaded_plugins.types,
+    )?;
+    if !report.is_clean() {
+        eprint!("{}", report);
+    }
+
+    let code = generate_code(&workspace.blocks)?;
+    for orphan in &code.orphans {
+        eprintln!("warning: {}", orphan);
+    }
+    let json = code.to_json()?;
+    match args.get(1) {
+        Some(output) => {
+            file_system.write_to_file(&absolute_path(output), json.as_bytes())?
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
@@ -1,15 +1,25 @@
 use fontdue::Font;
 
 use crate::{
-    internal::{Block, Camera},
+    all::index_by_block_id,
+    internal::{
+        block_input::{DROPDOWN_ARROW_WIDTH, LITERAL_PADDING},
+        block_kind::HAT_HEIGHT,
+        container::{CONTAINER_ARM_WIDTH, CONTAINER_BAR_HEIGHT},
+        dropdown::Dropdown,
+        palette::{PaletteRow, PALETTE_HEADER_HEIGHT, PALETTE_PADDING, PALETTE_WIDTH},
+        Block, BlockList, Camera, LiteralEditor,
+    },
     logic::Physics,
 };
 use mirl::{
     graphics::adjust_brightness_fast,
     platform::Buffer,
-    render::{draw_rectangle, draw_text_antialiased},
+    render::{draw_line, draw_rectangle, draw_text_antialiased},
 };
 
+const LITERAL_COLOR: u32 = mirl::graphics::rgb_to_u32(255, 255, 255);
+
 #[inline]
 pub fn draw_block<L: Physics>(
     block: &Block,
@@ -23,18 +33,29 @@ pub fn draw_block<L: Physics>(
     font: &Font,
     _logic: &L,
 ) {
-    let origin_x = origin_x - camera.x;
-    let origin_y = origin_y - camera.y;
+    let (origin_x, origin_y) = camera.to_screen((origin_x, origin_y));
+    let width = camera.scale(block.width.get());
+    let height = camera.scale(block.height.get());
+    let text_size = camera.scale_text((block.height.get() / 2.0) as f32);
 
+    if block.kind.has_hat() {
+        draw_hat(buffer, origin_x, origin_y, width, camera, block_color);
+    }
     draw_rectangle(
         buffer,
         origin_x,
         origin_y,
-        block.width.get() as isize,
-        block.height.get() as isize,
+        width,
+        height,
         block_color,
         true,
     );
+    if block.is_container() {
+        draw_container_arms(buffer, block, origin_x, origin_y, camera, block_color, font);
+    }
+    if block.kind.has_folded_corner() {
+        draw_folded_corner(buffer, origin_x + width, origin_y, camera, block_color);
+    }
     let length = block.name.len();
     let last_index;
     if length > 0 {
@@ -45,11 +66,10 @@ pub fn draw_block<L: Physics>(
             draw_text_antialiased(
                 buffer,
                 &block.name[i],
-                origin_x as usize
-                    + block.input_offsets.borrow()[i * 2] as usize,
+                (origin_x + camera.scale(block.input_offsets.borrow()[i * 2])) as usize,
                 origin_y as usize,
                 mirl::graphics::rgb_to_u32(255, 0, 0),
-                (block.height.get() / 2.0) as f32,
+                text_size,
                 font,
                 true,
             );
@@ -61,16 +81,41 @@ pub fn draw_block<L: Physics>(
             //     indexes
             // );
 
+            let input_x = origin_x + camera.scale(block.input_offsets.borrow()[i * 2 + 1]);
             draw_rectangle(
                 buffer,
-                origin_x + block.input_offsets.borrow()[i * 2 + 1] as isize,
-                origin_y + block.height.get() as isize / 10,
-                block.input_offsets.borrow()[i * 2 + 2] as isize
-                    - block.input_offsets.borrow()[i * 2 + 1] as isize,
-                block.height.get() as isize - block.height.get() as isize / 5,
+                input_x,
+                origin_y + camera.scale(block.height.get() / 10.0),
+                origin_x + camera.scale(block.input_offsets.borrow()[i * 2 + 2]) - input_x,
+                height - camera.scale(block.height.get() / 5.0),
                 adjust_brightness_fast(block_color, 50),
                 true,
             );
+            if block.stored_inputs.borrow()[i].is_none() {
+                if block.inputs[i].is_dropdown() {
+                    draw_dropdown_arrow(
+                        buffer,
+                        origin_x
+                            + camera.scale(
+                                block.input_offsets.borrow()[i * 2 + 2] - DROPDOWN_ARROW_WIDTH,
+                            ),
+                        origin_y + camera.scale(block.height.get() / 2.0 - 2.0),
+                        camera,
+                    );
+                }
+                if let Some(literal) = &block.stored_literals.borrow()[i] {
+                    draw_text_antialiased(
+                        buffer,
+                        block.inputs[i].label_of(literal),
+                        (input_x + camera.scale(LITERAL_PADDING)) as usize,
+                        origin_y as usize,
+                        LITERAL_COLOR,
+                        text_size,
+                        font,
+                        true,
+                    );
+                }
+            }
         }
     } else {
         last_index = 0;
@@ -80,11 +125,10 @@ pub fn draw_block<L: Physics>(
         draw_text_antialiased(
             buffer,
             &block.name[last_index],
-            (origin_x - camera.x) as usize
-                + block.input_offsets.borrow()[l - 1] as usize,
-            (origin_y - camera.y) as usize,
+            (origin_x + camera.scale(block.input_offsets.borrow()[l - 1])) as usize,
+            origin_y as usize,
             mirl::graphics::rgb_to_u32(255, 0, 0),
-            20.0,
+            text_size,
             font,
             true,
         );
@@ -102,3 +146,340 @@ pub fn draw_block<L: Physics>(
     //     font,
     // );
 }
+
+/// The left side and the bars below each slot, the bars between slots show the label of the next slot
+///
+/// `origin_x` and `origin_y` are already on the screen, the sizes are scaled by the zoom of the camera
+fn draw_container_arms(
+    buffer: &Buffer,
+    block: &Block,
+    origin_x: isize,
+    origin_y: isize,
+    camera: &Camera,
+    block_color: u32,
+    font: &Font,
+) {
+    let height = camera.scale(block.height.get());
+    draw_rectangle(
+        buffer,
+        origin_x,
+        origin_y + height,
+        camera.scale(CONTAINER_ARM_WIDTH),
+        camera.scale(block.full_height()) - height,
+        block_color,
+        true,
+    );
+    let bar_height = camera.scale(CONTAINER_BAR_HEIGHT);
+    for slot in 0..block.slots.len() {
+        let bar_y = origin_y + camera.scale(block.slot_offset_y(slot + 1)) - bar_height;
+        draw_rectangle(
+            buffer,
+            origin_x,
+            bar_y,
+            camera.scale(block.width.get()),
+            bar_height,
+            block_color,
+            true,
+        );
+        if let Some(next) = block.slots.get(slot + 1) {
+            draw_text_antialiased(
+                buffer,
+                &next.label,
+                (origin_x + camera.scale(CONTAINER_ARM_WIDTH)) as usize,
+                bar_y as usize,
+                mirl::graphics::rgb_to_u32(255, 0, 0),
+                camera.scale_text((CONTAINER_BAR_HEIGHT * 0.75) as f32),
+                font,
+                true,
+            );
+        }
+    }
+}
+
+/// Rounded bump on top of event blocks, showing nothing can go above them
+fn draw_hat(buffer: &Buffer, x: isize, y: isize, block_width: isize, camera: &Camera, color: u32) {
+    let height = camera.scale(HAT_HEIGHT).max(1);
+    let width = block_width.min(camera.scale(80.0));
+    for row in 0..height {
+        // Steeper towards the top, like a dome
+        let inset = (height - row) * (height - row) * width / (3 * height * height);
+        draw_rectangle(buffer, x + inset, y - height + row, width - inset * 2, 1, color, true);
+    }
+}
+
+/// Darker triangle in the top right corner of comments, `x` being their right edge
+fn draw_folded_corner(buffer: &Buffer, x: isize, y: isize, camera: &Camera, color: u32) {
+    let size = camera.scale(8.0);
+    for row in 0..size {
+        draw_rectangle(buffer, x - size, y + row, row + 1, 1, adjust_brightness_fast(color, -40), true);
+    }
+}
+/// Small downwards pointing triangle marking dropdown inputs
+fn draw_dropdown_arrow(buffer: &Buffer, x: isize, y: isize, camera: &Camera) {
+    let size = camera.scale(4.0);
+    for row in 0..size {
+        draw_rectangle(buffer, x + row, y + row, size * 2 - row * 2, 1, LITERAL_COLOR, true);
+    }
+}
+
+/// Draws the option list of an open dropdown below its input, the filter typed so far in the first row
+pub fn render_dropdown(
+    buffer: &Buffer,
+    dropdown: &Dropdown,
+    blocks: &BlockList,
+    camera: &Camera,
+    font: &Font,
+) {
+    let (Some((_, input)), Some(layout)) =
+        (dropdown.input(blocks), dropdown.layout(blocks, font))
+    else {
+        return;
+    };
+    let visible = dropdown.visible_options(input);
+    let (x, y) = camera.to_screen((layout.x, layout.y));
+    let width = camera.scale(layout.width);
+    let row_height = camera.scale(layout.row_height);
+    let font_size = camera.scale_text(layout.font_size);
+    let text_x = (x + camera.scale(LITERAL_PADDING)) as usize;
+
+    draw_rectangle(
+        buffer,
+        x,
+        y,
+        width,
+        row_height * (visible.len() as isize + 1),
+        mirl::graphics::rgb_to_u32(40, 40, 40),
+        true,
+    );
+    let (filter, filter_color) = if dropdown.filter.is_empty() {
+        ("Type to filter", mirl::graphics::rgb_to_u32(140, 140, 140))
+    } else {
+        (dropdown.filter.as_str(), LITERAL_COLOR)
+    };
+    draw_text_antialiased(buffer, filter, text_x, y as usize, filter_color, font_size, font, true);
+    for (row, (_, label)) in visible.iter().enumerate() {
+        let row_y = y + row_height * (row as isize + 1);
+        if row == dropdown.highlighted() {
+            draw_rectangle(
+                buffer,
+                x,
+                row_y,
+                width,
+                row_height,
+                mirl::graphics::rgb_to_u32(50, 90, 170),
+                true,
+            );
+        }
+        draw_text_antialiased(buffer, label, text_x, row_y as usize, LITERAL_COLOR, font_size, font, true);
+    }
+}
+
+/// Draws the input being typed into again, with its selection and caret
+pub fn render_literal_editor(
+    buffer: &Buffer,
+    editor: &LiteralEditor,
+    blocks: &BlockList,
+    camera: &Camera,
+    font: &Font,
+) {
+    let Some(idx) = index_by_block_id(&editor.block, blocks) else {
+        return;
+    };
+    let block = &blocks[idx];
+    let offsets = block.input_offsets.borrow();
+    let (Some(start), Some(end)) = (
+        offsets.get(editor.input_idx * 2 + 1),
+        offsets.get(editor.input_idx * 2 + 2),
+    ) else {
+        return;
+    };
+    let height = block.height.get();
+    let font_size = camera.scale_text((height / 2.0) as f32);
+    let (block_x, block_y) = camera.to_screen((block.x.get() as isize, block.y.get() as isize));
+    let slot_x = block_x + camera.scale(*start);
+    let slot_y = block_y + camera.scale(height / 10.0);
+    let slot_height = camera.scale(height) - camera.scale(height / 5.0);
+    let text_x = slot_x + camera.scale(LITERAL_PADDING);
+    let x_of = |char_index: usize| {
+        text_x
+            + mirl::render::get_length_of_string(editor.text_before(char_index), font_size, font)
+                as isize
+    };
+
+    draw_rectangle(
+        buffer,
+        slot_x,
+        slot_y,
+        block_x + camera.scale(*end) - slot_x,
+        slot_height,
+        mirl::graphics::rgb_to_u32(40, 40, 40),
+        true,
+    );
+    if let Some((selection_start, selection_end)) = editor.selection() {
+        draw_rectangle(
+            buffer,
+            x_of(selection_start),
+            slot_y,
+            x_of(selection_end) - x_of(selection_start),
+            slot_height,
+            mirl::graphics::rgb_to_u32(50, 90, 170),
+            true,
+        );
+    }
+    draw_text_antialiased(
+        buffer,
+        &editor.text,
+        text_x as usize,
+        block_y as usize,
+        LITERAL_COLOR,
+        font_size,
+        font,
+        true,
+    );
+    draw_rectangle(
+        buffer,
+        x_of(editor.caret()),
+        slot_y,
+        2,
+        slot_height,
+        LITERAL_COLOR,
+        true,
+    );
+}
+
+/// Draws the sidebar over the workspace, rows only partly on screen are left out
+pub fn render_palette<L: Physics>(
+    buffer: &Buffer,
+    rows: &[PaletteRow],
+    block_colors: &[u32],
+    font: &Font,
+    logic: &L,
+) {
+    draw_rectangle(
+        buffer,
+        0,
+        0,
+        PALETTE_WIDTH,
+        buffer.height as isize,
+        mirl::graphics::rgb_to_u32(40, 40, 40),
+        true,
+    );
+    let on_screen = |top: isize, bottom: isize| top >= 0 && bottom <= buffer.height as isize;
+    for row in rows {
+        match row {
+            PaletteRow::Header { plugin, output, color_id, y } => {
+                if !on_screen(*y, y + PALETTE_HEADER_HEIGHT) {
+                    continue;
+                }
+                draw_rectangle(
+                    buffer,
+                    PALETTE_PADDING,
+                    *y,
+                    PALETTE_WIDTH - PALETTE_PADDING * 2,
+                    PALETTE_HEADER_HEIGHT,
+                    adjust_brightness_fast(block_colors[*color_id], -40),
+                    true,
+                );
+                draw_text_antialiased(
+                    buffer,
+                    &format!("{} - {}", plugin, output),
+                    (PALETTE_PADDING * 2) as usize,
+                    *y as usize,
+                    LITERAL_COLOR,
+                    (PALETTE_HEADER_HEIGHT as f32) * 0.75,
+                    font,
+                    true,
+                );
+            }
+            PaletteRow::Block { block, x, y } => {
+                if !on_screen(*y, y + block.full_height() as isize) {
+                    continue;
+                }
+                // Not zoomed along with the workspace, the palette stays as it is
+                draw_block(
+                    block,
+                    *x,
+                    *y,
+                    &Camera { x: 0, y: 0, z: 1.0 },
+                    buffer,
+                    block_colors[block.block_color_id],
+                    buffer.width,
+                    buffer.height,
+                    font,
+                    logic,
+                );
+            }
+        }
+    }
+}
+
+/// Size of the square blocks are dropped onto to delete them
+const TRASH_SIZE: isize = 64;
+/// Space between the trash and the corner of the window
+const TRASH_MARGIN: isize = 16;
+
+/// Screen position and size of the trash in the bottom right corner
+pub fn trash_area(buffer: &Buffer) -> (isize, isize, isize, isize) {
+    (
+        buffer.width as isize - TRASH_SIZE - TRASH_MARGIN,
+        buffer.height as isize - TRASH_SIZE - TRASH_MARGIN,
+        TRASH_SIZE,
+        TRASH_SIZE,
+    )
+}
+
+/// Drawn while dragging a block, red while the block would be deleted when let go
+pub fn render_trash(buffer: &Buffer, hovered: bool) {
+    let (x, y, width, height) = trash_area(buffer);
+    let color = if hovered {
+        mirl::graphics::rgb_to_u32(200, 50, 50)
+    } else {
+        mirl::graphics::rgb_to_u32(60, 60, 60)
+    };
+    draw_rectangle(buffer, x, y, width, height, color, true);
+    let padding = TRASH_SIZE / 4;
+    let (left, top) = ((x + padding) as usize, (y + padding) as usize);
+    let (right, bottom) = ((x + width - padding) as usize, (y + height - padding) as usize);
+    let cross = mirl::graphics::rgb_to_u32(255, 255, 255);
+    draw_line(buffer, (left, top), (right, bottom), cross, 2, true);
+    draw_line(buffer, (left, bottom), (right, top), cross, 2, true);
+}
+
+/// Lines of text in the top left corner, used for errors the user should see without a console
+pub fn render_messages(buffer: &Buffer, messages: &[String], font: &Font) {
+    let size = 16.0;
+    if messages.is_empty() {
+        return;
+    }
+    draw_rectangle(
+        buffer,
+        0,
+        0,
+        buffer.width as isize,
+        (messages.len() + 1) as isize * size as isize,
+        mirl::graphics::rgb_to_u32(30, 30, 30),
+        true,
+    );
+    for (idx, message) in messages.iter().enumerate() {
+        draw_text_antialiased(
+            buffer,
+            message,
+            4,
+            idx * size as usize,
+            mirl::graphics::rgb_to_u32(255, 90, 90),
+            size,
+            font,
+            true,
+        );
+    }
+    draw_text_antialiased(
+        buffer,
+        "Press Escape to dismiss",
+        4,
+        messages.len() * size as usize,
+        mirl::graphics::rgb_to_u32(180, 180, 180),
+        size,
+        font,
+        true,
+    );
+}
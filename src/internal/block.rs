@@ -1,5 +1,4 @@
 use fontdue::Font;
-use mirl::extensions::TupleOps;
 use num_traits::ToPrimitive;
 use std::cell::Cell;
 use std::cell::RefCell;
@@ -8,7 +7,10 @@ use crate::all::get_bottom_most_block_idx_or_self;
 use crate::all::get_top_most_block_idx_or_self;
 use crate::all::index_by_block_id;
 use crate::internal::id::UsizeGetID;
+use crate::internal::container::StatementSlot;
 use crate::internal::BlockInput;
+use crate::internal::BlockKind;
+use crate::internal::BlockList;
 use crate::internal::WorkSpace;
 use crate::internal::ID;
 use crate::logic::Physics;
@@ -28,6 +30,8 @@ pub struct Block {
     pub original_name: String,
     /// Internal name for file accessing
     pub internal_name: String,
+    /// Folder name of the plugin defining this block, empty if it didn't come from one
+    pub plugin: String,
     #[debug("{:?}",x.get())]
     /// X coordinate
     pub x: Cell<CoordinateType>,
@@ -40,12 +44,7 @@ pub struct Block {
     #[debug("{:?}",height.get())]
     /// Height
     pub height: Cell<SizeType>,
-    /// 0: Action (Main structure blocks)
-    ///
-    /// 1: Inline (Inputs)
-    ///
-    /// 2: Event (Entry points)
-    pub block_type: u8,
+    pub kind: BlockKind,
     pub required_imports: Vec<String>,
     pub required_contexts: Vec<String>,
     pub file_versions: Vec<String>,
@@ -55,6 +54,23 @@ pub struct Block {
     pub input_offsets: RefCell<Vec<SizeType>>,
     #[debug("{:?}",stored_inputs.borrow())]
     pub stored_inputs: RefCell<Vec<Option<ID>>>,
+    #[debug("{:?}",stored_literals.borrow())]
+    /// Typed in values for inputs without a stored block
+    pub stored_literals: RefCell<Vec<Option<String>>>,
+    #[debug("{:?}",stored_in.get())]
+    /// The block and input index this block is stored in, if any
+    pub stored_in: Cell<Option<(ID, usize)>>,
+    /// Statement slots of containers, empty for every other block
+    pub slots: Vec<StatementSlot>,
+    #[debug("{:?}",stored_slots.borrow())]
+    /// First block of the stack in each slot
+    pub stored_slots: RefCell<Vec<Option<ID>>>,
+    #[debug(skip)]
+    /// Height of the stack in each slot, updated by [`Block::layout_stack`]
+    pub slot_heights: RefCell<Vec<SizeType>>,
+    #[debug("{:?}",in_slot.get())]
+    /// The container and slot index this block heads the stack of, if any
+    pub in_slot: Cell<Option<(ID, usize)>>,
     #[debug(skip)]
     pub block_color_id: usize,
     #[debug("{id:?}")]
@@ -69,9 +85,45 @@ pub struct Block {
     pub possible_connection_above: Cell<Option<ID>>,
     #[debug(skip)]
     pub possible_connection_below: Cell<Option<ID>>,
+    #[debug(skip)]
+    /// Input the block would be stored in when let go
+    pub possible_input: Cell<Option<(ID, usize)>>,
+    #[debug(skip)]
+    /// Container slot the block would head when let go
+    pub possible_slot: Cell<Option<(ID, usize)>>,
     #[debug("{:?}", recently_moved.get())]
     pub recently_moved: Cell<bool>,
 }
+/// Why a [`Block`] could not be created
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlockError {
+    /// The name has a different amount of `{}` than there are inputs
+    InputCountMismatch { placeholders: usize, inputs: usize },
+}
+impl std::fmt::Display for BlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::InputCountMismatch { placeholders, inputs } => write!(
+                f,
+                "name expects {} inputs while {} were provided",
+                placeholders, inputs
+            ),
+        }
+    }
+}
+impl std::error::Error for BlockError {}
+
+/// Text between the inputs - One more segment than there are inputs
+fn split_name(name: &str, input_count: usize) -> Result<Vec<String>, BlockError> {
+    if name.matches("{}").count() != input_count {
+        return Err(BlockError::InputCountMismatch {
+            placeholders: name.matches("{}").count(),
+            inputs: input_count,
+        });
+    }
+    Ok(name.split("{}").map(|x| x.to_string()).collect())
+}
+
 use std::ops::Div;
 impl Block {
     pub fn new<L: Physics>(
@@ -79,7 +131,7 @@ impl Block {
         internal_name: String,
         x: CoordinateTypeSigned,
         y: CoordinateTypeSigned,
-        block_type: u8,
+        kind: BlockKind,
         required_imports: Vec<String>,
         required_contexts: Vec<String>,
         file_versions: Vec<String>,
@@ -89,7 +141,7 @@ impl Block {
         font: &Font,
         workspace: &mut WorkSpace<L>,
         overwrite_id: Option<ID>,
-    ) -> Block {
+    ) -> Result<Block, BlockError> {
         let color_id = output_color_names
             .iter()
             .position(|x| *x == output)
@@ -98,27 +150,14 @@ impl Block {
         let x = x.map_sign_to_non_sign();
         let y = y.map_sign_to_non_sign();
 
-        if name.matches("{}").count() != inputs.len() {
-            panic!(
-                "Translation name expects {} inputs while an unfitting {} were provided to the functions for '{}' \n(Raw name: '{}'\nRaw given inputs: '{:#?}')",
-                name.matches("{}").count(),
-                inputs.len(),
-                internal_name,
-                name,inputs
-            )
-        }
-        let text_between_inputs: Vec<String> = name
-            .split("{}")
-            .collect::<Vec<&str>>()
-            .iter()
-            .map(|x| x.to_string())
-            .collect();
+        let text_between_inputs = split_name(&name, inputs.len())?;
 
         let input_offsets = Vec::new();
         let mut stored = Vec::new();
         for _ in 0..inputs.len() {
             stored.push(None)
         }
+        let literals = inputs.iter().map(BlockInput::default_literal).collect();
 
         // for i in name.split("{}") {
         //     // Keep the i == "" because otherwise rendering is wonky ._.
@@ -133,17 +172,24 @@ impl Block {
             name: text_between_inputs,
             original_name: name,
             internal_name,
+            plugin: String::new(),
             x: Cell::new(x),
             y: Cell::new(y),
             width: Cell::new(0.0),
             height: Cell::new(40.0),
-            block_type,
+            kind,
             required_imports,
             required_contexts,
             file_versions,
             output,
             inputs,
+            stored_literals: RefCell::new(literals),
             stored_inputs: RefCell::new(stored),
+            stored_in: Cell::new(None),
+            slots: Vec::new(),
+            stored_slots: RefCell::new(Vec::new()),
+            slot_heights: RefCell::new(Vec::new()),
+            in_slot: Cell::new(None),
             input_offsets: RefCell::new(input_offsets),
             block_color_id: color_id,
             id: if let Some(overwrite_id) = overwrite_id {
@@ -156,11 +202,106 @@ impl Block {
             connected_below: Cell::new(None),
             possible_connection_above: Cell::new(None),
             possible_connection_below: Cell::new(None),
+            possible_input: Cell::new(None),
+            possible_slot: Cell::new(None),
             recently_moved: Cell::new(false),
         };
-        b.recalculate_input_offsets(font);
+        b.recalculate_input_offsets(font, &BlockList::new());
         b.recalculate_width(font);
-        b
+        Ok(b)
+    }
+    /// Swaps the displayed name, like after changing the language
+    ///
+    /// The old name is kept if the new one doesn't fit the inputs - Lay it out again with [`Block::relayout_with_inputs`] afterwards
+    pub fn rename(&mut self, name: String) -> Result<(), BlockError> {
+        self.name = split_name(&name, self.inputs.len())?;
+        self.original_name = name;
+        Ok(())
+    }
+    /// Lays out the blocks stored in the inputs first (recursively), then this block around them
+    pub fn relayout_with_inputs(&self, blocks: &BlockList, font: &Font) {
+        for stored in self.stored_inputs.borrow().iter().flatten() {
+            if let Some(idx) = index_by_block_id(stored, blocks) {
+                blocks[idx].relayout_with_inputs(blocks, font);
+            }
+        }
+        self.recalculate_input_offsets(font, blocks);
+        self.recalculate_width_in(blocks, font);
+    }
+    /// Lays out this block and every block it is (indirectly) stored in, for when one of its inputs changed size
+    pub fn relayout_hosts(&self, blocks: &BlockList, font: &Font) {
+        self.recalculate_input_offsets(font, blocks);
+        self.recalculate_width_in(blocks, font);
+        if let Some((host, _)) = self.stored_in.get() {
+            if let Some(idx) = index_by_block_id(&host, blocks) {
+                blocks[idx].relayout_hosts(blocks, font);
+            }
+        }
+    }
+    /// Stores this block in input `input_idx` of `host_id` and grows the host to fit it
+    pub fn attach_to_input(
+        &self,
+        host_id: ID,
+        input_idx: usize,
+        blocks: &BlockList,
+        font: &Font,
+    ) -> bool {
+        let Some(host_idx) = index_by_block_id(&host_id, blocks) else {
+            return false;
+        };
+        let host = &blocks[host_idx];
+        blocks.touch(host);
+        blocks.touch(self);
+        match host.stored_inputs.borrow_mut().get_mut(input_idx) {
+            Some(slot @ None) => *slot = Some(self.id),
+            _ => return false,
+        }
+        self.stored_in.set(Some((host_id, input_idx)));
+        host.relayout_hosts(blocks, font);
+        self.position_stored_inputs(blocks);
+        true
+    }
+    /// Takes this block out of the input it is stored in, shrinking the host again
+    pub fn detach_from_input(&self, blocks: &BlockList, font: &Font) {
+        let Some((host_id, input_idx)) = self.stored_in.get() else {
+            return;
+        };
+        blocks.touch(self);
+        self.stored_in.set(None);
+        if let Some(host_idx) = index_by_block_id(&host_id, blocks) {
+            let host = &blocks[host_idx];
+            blocks.touch(host);
+            if let Some(slot) = host.stored_inputs.borrow_mut().get_mut(input_idx) {
+                if *slot == Some(self.id) {
+                    *slot = None;
+                }
+            }
+            host.relayout_hosts(blocks, font);
+        }
+    }
+    /// Moves every stored block (recursively) onto its input of this block
+    pub fn position_stored_inputs(&self, blocks: &BlockList) {
+        let offsets = self.input_offsets.borrow();
+        for (input_idx, stored) in self.stored_inputs.borrow().iter().enumerate() {
+            let Some(stored) = stored else {
+                continue;
+            };
+            let Some(idx) = index_by_block_id(stored, blocks) else {
+                continue;
+            };
+            let stored = &blocks[idx];
+            let offset = offsets.get(input_idx * 2 + 1).copied().unwrap_or_default();
+            blocks.set_position(stored, self.x.get() + offset as CoordinateType, self.y.get());
+            stored.position_stored_inputs(blocks);
+        }
+    }
+    /// [`recalculate_width`](Block::recalculate_width) for a block of `blocks`, telling its spatial index when the width changed
+    fn recalculate_width_in(&self, blocks: &BlockList, font: &Font) {
+        let width = self.width.get();
+        self.recalculate_width(font);
+        if self.width.get() != width {
+            blocks.mark_moved(self);
+        }
     }
     pub fn recalculate_width(&self, font: &Font) {
         let mut width: SizeType = 0.0;
@@ -176,11 +317,12 @@ impl Block {
         // Get the last known input offset, add the width of the input, and add the letters after the input
         self.width.set(width);
     }
-    pub fn disconnect_below(&self, blocks: &Vec<Block>) {
+    pub fn disconnect_below(&self, blocks: &BlockList) {
         if let Some(below) = self.connected_below.get() {
             let index_of_connected_below =
                 index_by_block_id(&below, blocks).unwrap();
             let block_below = &blocks[index_of_connected_below];
+            blocks.touch(block_below);
             block_below.connected_top.set(None);
             block_below.connected_above.set(None);
             block_below.update_topmost(blocks, true);
@@ -189,18 +331,22 @@ impl Block {
     /// Sets [`connected_below`](Block::connected_below) of [`connected_above`](Block::connected_above) to None
     ///
     /// Updates the [`connected_top`](Block::connected_top) of all connected blocks
-    pub fn disconnect_above(&self, blocks: &Vec<Block>) {
+    pub fn disconnect_above(&self, blocks: &BlockList) {
         if let Some(above) = self.connected_above.get() {
             if let Some(idx) = index_by_block_id(&above, blocks) {
                 let block_above = &blocks[idx];
+                blocks.touch(block_above);
+                blocks.touch(self);
                 block_above.connected_below.set(None);
                 self.connected_above.set(None);
+                // The stack left behind got shorter, a container holding it shrinks
+                blocks.mark_moved(block_above);
             }
         }
 
         self.recursive_set_topmost(blocks, self.id, true);
     }
-    pub fn connect_below_to_above(&self, blocks: &Vec<Block>) -> bool {
+    pub fn connect_below_to_above(&self, blocks: &BlockList) -> bool {
         if self.connected_above.get().is_none() {
             self.disconnect_below(blocks);
             return false;
@@ -217,6 +363,9 @@ impl Block {
             {
                 let block_above = &blocks[block_idx_above];
                 let block_below = &blocks[block_idx_below];
+                blocks.touch(self);
+                blocks.touch(block_above);
+                blocks.touch(block_below);
 
                 // Disconnect self from all others
                 self.connected_top.set(None);
@@ -234,7 +383,7 @@ impl Block {
         }
         false
     }
-    pub fn update_topmost(&self, blocks: &Vec<Block>, set_moved: bool) {
+    pub fn update_topmost(&self, blocks: &BlockList, set_moved: bool) {
         let new_id = get_top_most_block_idx_or_self(
             blocks,
             index_by_block_id(&self.id, blocks).unwrap(),
@@ -247,17 +396,17 @@ impl Block {
     }
     pub fn recursive_set_topmost(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
         new_id: ID,
         set_moved: bool,
     ) -> bool {
         if set_moved {
             self.recently_moved.set(true)
         }
-        if self.id != new_id {
-            self.connected_top.set(Some(new_id));
-        } else {
-            self.connected_top.set(None);
+        let top = Some(new_id).filter(|x| *x != self.id);
+        if self.connected_top.get() != top {
+            blocks.touch(self);
+            self.connected_top.set(top);
         }
         if let Some(below) = self.connected_below.get() {
             if let Some(below_idx) = index_by_block_id(&below, blocks) {
@@ -268,8 +417,16 @@ impl Block {
         }
         false
     }
-    pub fn connect_to_block(&self, block_idx: usize, blocks: &Vec<Block>) {
+    /// Inserts this block (and what is below it) under the block at `block_idx` - Blocks that can't go below anything stay where they are
+    pub fn connect_to_block(&self, block_idx: usize, blocks: &BlockList) {
+        if !self.kind.can_connect_above() {
+            return;
+        }
         let block_above = &blocks[block_idx];
+        blocks.touch(block_above);
+        blocks.touch(self);
+        // Laid out into its new stack even when it was let go exactly where it ends up
+        blocks.mark_moved(self);
         let block_below_id = block_above.connected_below.get();
         block_above.connected_below.set(Some(self.id));
         self.connected_above.set(Some(block_above.id));
@@ -283,20 +440,25 @@ impl Block {
                     blocks,
                     index_by_block_id(&self.id, blocks).unwrap(),
                 )];
-                bottom_most.connected_below.set(Some(block_below_id));
                 let block_below = &blocks[block_below_idx];
+                blocks.touch(bottom_most);
+                blocks.touch(block_below);
+                bottom_most.connected_below.set(Some(block_below_id));
                 block_below.connected_above.set(Some(self.id))
             }
         }
     }
-    pub fn connect_to_possibly_above(&self, blocks: &Vec<Block>) {
+    pub fn connect_to_possibly_above(&self, blocks: &BlockList) {
         if let Some(connection_id) = self.possible_connection_above.get() {
             if let Some(idx) = index_by_block_id(&connection_id, blocks) {
                 self.connect_to_block(idx, blocks);
             }
         }
     }
-    pub fn recalculate_input_offsets(&self, font: &Font) {
+    /// Inputs holding a block are as wide as that block, the others as wide as their literal
+    ///
+    /// `blocks` may be empty for blocks that can't hold any yet
+    pub fn recalculate_input_offsets(&self, font: &Font, blocks: &BlockList) {
         let mut offsets: Vec<SizeType> = Vec::new();
         let mut total_offset = 0.0;
         let loop_amount = self.name.len() - 1;
@@ -315,8 +477,17 @@ impl Block {
                 offsets.push(total_offset);
                 // Get width of input
                 let input = &self.inputs[i];
+                let stored = self.stored_inputs.borrow()[i]
+                    .and_then(|x| index_by_block_id(&x, blocks));
                 // Add offset of input
-                total_offset += input.get_width(font);
+                total_offset += match stored {
+                    Some(idx) => blocks[idx].width.get(),
+                    None => input.get_width(
+                        self.stored_literals.borrow()[i].as_deref(),
+                        self.height.get().div(2.0).to_f32().unwrap(),
+                        font,
+                    ),
+                };
                 // Add offset of input
                 offsets.push(total_offset);
             }
@@ -334,7 +505,31 @@ impl Block {
             self.input_offsets.swap(&RefCell::new(offsets));
         }
     }
-    pub fn get_all_ids_of_all_inputs(&self, blocks: &Vec<Block>) -> Vec<ID> {
+    /// Sets (or with None clears) the literal of an input and resizes this block and its hosts
+    pub fn set_literal(
+        &self,
+        input_idx: usize,
+        literal: Option<String>,
+        blocks: &BlockList,
+        font: &Font,
+    ) {
+        blocks.touch(self);
+        if let Some(stored) = self.stored_literals.borrow_mut().get_mut(input_idx) {
+            *stored = literal;
+        }
+        self.relayout_hosts(blocks, font);
+    }
+    /// Index of the input without a stored block at `pos_x` (world coordinates), for typing a literal into it
+    pub fn get_empty_input_at(&self, pos_x: isize) -> Option<usize> {
+        let offsets = self.input_offsets.borrow();
+        let relative = (pos_x - self.x.get() as isize) as SizeType;
+        (0..self.inputs.len()).find(|i| {
+            self.stored_inputs.borrow()[*i].is_none()
+                && offsets.get(i * 2 + 1).is_some_and(|x| *x <= relative)
+                && offsets.get(i * 2 + 2).is_some_and(|x| relative < *x)
+        })
+    }
+    pub fn get_all_ids_of_all_inputs(&self, blocks: &BlockList) -> Vec<ID> {
         let mut found = Vec::new();
         for some_id in self.stored_inputs.borrow().iter().flatten() {
             found.push(*some_id);
@@ -352,14 +547,30 @@ impl Block {
         range: SizeType,
         logic: &L,
         backlist: &[ID],
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
+        accepts: &dyn Fn(&Block, usize) -> bool,
     ) -> Option<InputRememberer> {
         if backlist.contains(&self.id) {
             return None;
         }
         let mut found = Vec::new();
         for i in 0..self.inputs.len() {
-            let item = self.input_offsets.borrow()[i * 2];
+            // Start of the input itself, not of the text before it
+            let item = self.input_offsets.borrow()[i * 2 + 1];
+            if let Some(stored_input) = self.stored_inputs.borrow()[i] {
+                // Stored blocks have their own position, only their empty inputs can be dropped into
+                let Some(stored_idx) = index_by_block_id(&stored_input, blocks) else {
+                    continue;
+                };
+                found.push((
+                    i,
+                    blocks[stored_idx].get_inputs_in_range(
+                        point, offset, range, logic, backlist, blocks, accepts,
+                    ),
+                    None,
+                ));
+                continue;
+            }
             let point_x = self.x.get() as SizeType + item;
             // This checks the corner of the boxes, not the middle
             let distance = logic.get_distance_between_positions(
@@ -368,26 +579,10 @@ impl Block {
                 point_x as SizeType,
                 self.y.get() as SizeType,
             );
-            if distance > range {
+            if distance > range || !accepts(self, i) {
                 continue;
             }
-            if let Some(stored_input) = self.stored_inputs.borrow()[i] {
-                found.push((
-                    i,
-                    blocks[index_by_block_id(&stored_input, blocks).unwrap()]
-                        .get_inputs_in_range(
-                            point,
-                            offset.add((item, 0.0)),
-                            range,
-                            logic,
-                            backlist,
-                            blocks,
-                        ),
-                    Some(distance),
-                ))
-            } else {
-                found.push((i, None, Some(distance)))
-            }
+            found.push((i, None, Some(distance)))
         }
         let ir = InputRememberer {
             internal_inputs: found,
@@ -397,7 +592,7 @@ impl Block {
     }
     pub fn create_input_rememberer(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
     ) -> InputRememberer {
         let mut found = Vec::new();
         for (idx, id) in self.stored_inputs.borrow().iter().enumerate() {
@@ -423,13 +618,13 @@ impl Block {
         output_color_names: &[String],
         font: &Font,
         workspace: &mut WorkSpace<L>,
-    ) -> Self {
-        Self::new(
+    ) -> Result<Self, BlockError> {
+        let mut block = Self::new(
             self.original_name.clone(),
             self.internal_name.clone(),
             self.x.get().map_non_sign_to_sign(),
             self.y.get().map_non_sign_to_sign(),
-            self.block_type,
+            self.kind,
             self.required_imports.clone(),
             self.required_contexts.clone(),
             self.file_versions.clone(),
@@ -439,7 +634,10 @@ impl Block {
             font,
             workspace,
             None,
-        )
+        )?;
+        block.plugin = self.plugin.clone();
+        block.set_slots(self.slots.clone());
+        Ok(block)
     }
 }
 pub struct InputRememberer {
@@ -1,13 +1,20 @@
-// use crate::all::get_length_of_text_in_font;
-
 use crate::SizeType;
 
+/// Width of an input holding nothing
+pub const EMPTY_INPUT_WIDTH: SizeType = 20.0;
+/// Space left and right of a typed in literal
+pub const LITERAL_PADDING: SizeType = 4.0;
+/// Extra room on the right of dropdown inputs for the arrow
+pub const DROPDOWN_ARROW_WIDTH: SizeType = 12.0;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct BlockInput {
     input_type: String,
     block_id: Option<usize>,
     literal_allowed: Vec<String>,
     literal_return: Vec<String>,
+    /// Translated text shown for each of `literal_allowed`
+    literal_labels: Vec<String>,
 }
 impl BlockInput {
     pub fn new(
@@ -25,15 +32,76 @@ impl BlockInput {
         Ok(Self {
             block_id,
             input_type,
+            literal_labels: literal_allowed.clone(),
             literal_allowed,
             literal_return,
         })
     }
-    pub fn get_width(&self, _font: &fontdue::Font) -> SizeType {
-        if self.block_id.is_none() {
-            return 20.0;
+    /// Type of the blocks this input accepts
+    pub fn input_type(&self) -> &str {
+        &self.input_type
+    }
+    /// Inputs with `expected` literals are picked from a list instead of typed into
+    pub fn is_dropdown(&self) -> bool {
+        !self.literal_allowed.is_empty()
+    }
+    /// Literal a new block starts with, the first option for dropdowns
+    pub fn default_literal(&self) -> Option<String> {
+        self.literal_allowed.first().cloned()
+    }
+    /// Allowed literals together with their translated label, sorted by literal as `expected` is read into a sorted map
+    pub fn options(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.literal_allowed
+            .iter()
+            .map(String::as_str)
+            .zip(self.literal_labels.iter().map(String::as_str))
+    }
+    /// Text shown for a stored literal, the literal itself if it has no label
+    pub fn label_of<'a>(&'a self, literal: &'a str) -> &'a str {
+        self.options()
+            .find(|x| x.0 == literal)
+            .map_or(literal, |x| x.1)
+    }
+    /// Relabels the options, `translate` gets the allowed literal (a translation key) - Untranslated ones show the key
+    pub fn translate_labels(&mut self, translate: impl Fn(&str) -> Option<String>) {
+        self.literal_labels = self
+            .literal_allowed
+            .iter()
+            .map(|x| translate(x).unwrap_or_else(|| x.clone()))
+            .collect();
+    }
+    /// Maps a literal found in `literal_allowed` to its `literal_return`, anything else is passed through as is
+    pub fn resolve_literal(&self, literal: &str) -> String {
+        if let Some(idx) = self.literal_allowed.iter().position(|x| x == literal) {
+            return self.literal_return[idx].clone();
+        }
+        literal.to_string()
+    }
+    /// Width of the input without a stored block, wide enough for the typed in `literal` or the label of the picked option
+    pub fn get_width(
+        &self,
+        literal: Option<&str>,
+        font_size: f32,
+        font: &fontdue::Font,
+    ) -> SizeType {
+        if self.block_id.is_some() {
+            return 10.0;
+        }
+        let arrow = if self.is_dropdown() {
+            DROPDOWN_ARROW_WIDTH
+        } else {
+            0.0
+        };
+        match literal.filter(|x| !x.is_empty()) {
+            Some(literal) => (mirl::render::get_length_of_string(
+                self.label_of(literal),
+                font_size,
+                font,
+            ) as SizeType
+                + LITERAL_PADDING * 2.0
+                + arrow)
+                .max(EMPTY_INPUT_WIDTH),
+            None => EMPTY_INPUT_WIDTH + arrow,
         }
-        10.0
-        //get_length_of_text_in_font(&self.name, font)
     }
 }
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+use crate::SizeType;
+
+/// How far the hat of an event reaches above the block
+pub const HAT_HEIGHT: SizeType = 12.0;
+
+/// What a block is for, deciding what it can connect to and how it is drawn
+///
+/// Serialized as the same lowercase name plugins use in their `"type"` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// Main structure blocks, stacked below each other
+    Action,
+    /// Put into the inputs of other blocks
+    Inline,
+    /// Entry points, each one starts a procedure
+    Event,
+    /// Stacked like actions, holding their own stacks in statement slots
+    Container,
+    /// Notes placed anywhere, connecting to nothing and left out of the generated code
+    Comment,
+}
+
+impl BlockKind {
+    /// Every kind, in the order they are listed to plugin authors
+    pub const ALL: [BlockKind; 5] = [
+        BlockKind::Action,
+        BlockKind::Inline,
+        BlockKind::Event,
+        BlockKind::Container,
+        BlockKind::Comment,
+    ];
+    /// Name used for the `"type"` of plugin blocks
+    pub fn name(self) -> &'static str {
+        match self {
+            BlockKind::Action => "action",
+            BlockKind::Inline => "inline",
+            BlockKind::Event => "event",
+            BlockKind::Container => "container",
+            BlockKind::Comment => "comment",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+    /// Forms stacks, other blocks can be connected below it
+    pub fn is_stackable(self) -> bool {
+        matches!(
+            self,
+            BlockKind::Action | BlockKind::Event | BlockKind::Container
+        )
+    }
+    /// Can be connected below another block or put into a slot
+    pub fn can_connect_above(self) -> bool {
+        matches!(self, BlockKind::Action | BlockKind::Container)
+    }
+    /// Goes into the inputs of other blocks instead of stacking
+    pub fn goes_into_inputs(self) -> bool {
+        self == BlockKind::Inline
+    }
+    /// Holds stacks in the slots listed by the plugin
+    pub fn has_slots(self) -> bool {
+        self == BlockKind::Container
+    }
+    /// A stack headed by it becomes a procedure
+    pub fn starts_procedure(self) -> bool {
+        self == BlockKind::Event
+    }
+    /// Drawn with a rounded bump on top, showing nothing can go above it
+    pub fn has_hat(self) -> bool {
+        self == BlockKind::Event
+    }
+    /// Drawn with a folded corner like a sticky note
+    pub fn has_folded_corner(self) -> bool {
+        self == BlockKind::Comment
+    }
+}
+
+impl std::fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::internal::spatial_grid::SpatialGrid;
+use crate::internal::{Block, ID};
+use crate::CoordinateType;
+
+/// The blocks of a workspace in drawing order together with where each id is, so finding a block by its id doesn't walk the whole list
+///
+/// Changes go through the methods below which keep everything in sync, reading works like a slice
+#[derive(Debug, Clone, Default)]
+pub struct BlockList {
+    blocks: Vec<Block>,
+    indexes: HashMap<ID, usize>,
+    /// Where the blocks are in the world, blocks moved since the last [`refresh_spatial_index`](BlockList::refresh_spatial_index) may still be in their old cells
+    grid: SpatialGrid,
+    /// Blocks moved or resized since the last refresh, blocks only hand out `&Block` so this is filled through `&self`
+    moved: RefCell<HashSet<ID>>,
+    /// The same since the stacks were last laid out, which happens after the refresh - Blocks just added and stacks that changed shape are in here too
+    moved_since_layout: RefCell<HashSet<ID>>,
+    /// While an edit records its changes, each block it changed as it was before the first change with its index then - None for blocks it added
+    touched: RefCell<Option<Touched>>,
+}
+
+/// Blocks before an edit changed them, see [`BlockList::touch`]
+pub type Touched = HashMap<ID, Option<(usize, Block)>>;
+
+impl BlockList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Where the block with this id is, None if there is none
+    #[inline]
+    pub fn index_of(&self, id: &ID) -> Option<usize> {
+        self.indexes.get(id).copied()
+    }
+    pub fn get_by_id(&self, id: &ID) -> Option<&Block> {
+        self.index_of(id).map(|x| &self.blocks[x])
+    }
+    pub fn push(&mut self, block: Block) {
+        self.touch_added(block.id);
+        self.grid.insert(&block);
+        self.moved_since_layout.get_mut().insert(block.id);
+        self.indexes.insert(block.id, self.blocks.len());
+        self.blocks.push(block);
+    }
+    /// Puts the block at `idx`, moving everything after it back by one
+    pub fn insert(&mut self, idx: usize, block: Block) {
+        self.touch_added(block.id);
+        self.grid.insert(&block);
+        self.moved_since_layout.get_mut().insert(block.id);
+        self.blocks.insert(idx, block);
+        self.reindex(idx..self.blocks.len());
+    }
+    pub fn remove(&mut self, idx: usize) -> Block {
+        self.touch(&self.blocks[idx]);
+        let block = self.blocks.remove(idx);
+        self.indexes.remove(&block.id);
+        self.grid.remove(&block.id);
+        self.reindex(idx..self.blocks.len());
+        block
+    }
+    /// Swaps the block at `idx` for another one, which may have a different id
+    pub fn replace(&mut self, idx: usize, block: Block) -> Block {
+        self.touch(&self.blocks[idx]);
+        self.touch_added(block.id);
+        let old = std::mem::replace(&mut self.blocks[idx], block);
+        self.indexes.remove(&old.id);
+        self.grid.remove(&old.id);
+        self.indexes.insert(self.blocks[idx].id, idx);
+        self.grid.insert(&self.blocks[idx]);
+        self.moved_since_layout.get_mut().insert(self.blocks[idx].id);
+        old
+    }
+    pub fn retain(&mut self, keep: impl FnMut(&Block) -> bool) {
+        let kept: Vec<bool> = self.blocks.iter().map(keep).collect();
+        for (block, _) in self.blocks.iter().zip(&kept).filter(|x| !x.1) {
+            self.touch(block);
+        }
+        let mut kept = kept.into_iter();
+        self.blocks.retain(|_| kept.next().unwrap_or(true));
+        self.indexes.clear();
+        self.reindex(0..self.blocks.len());
+        self.grid.clear();
+        for block in self.blocks.iter() {
+            self.grid.insert(block);
+        }
+    }
+    /// Moves the block at `from` to `to`, shifting the ones in between - Only those get their index updated
+    pub fn move_to(&mut self, from: usize, to: usize) {
+        if from == to || from >= self.blocks.len() || to >= self.blocks.len() {
+            return;
+        }
+        let block = self.blocks.remove(from);
+        self.blocks.insert(to, block);
+        self.reindex(from.min(to)..from.max(to) + 1);
+    }
+    /// Moves a block of this list, the spatial index catches up on the next refresh - Setting the position it already has costs nothing
+    pub fn set_position(&self, block: &Block, x: CoordinateType, y: CoordinateType) {
+        if block.x.get() == x && block.y.get() == y {
+            return;
+        }
+        self.touch(block);
+        block.x.set(x);
+        block.y.set(y);
+        self.mark_moved(block);
+    }
+    /// For blocks whose bounds changed without [`set_position`](BlockList::set_position), like when they got wider, or whose stack changed
+    pub fn mark_moved(&self, block: &Block) {
+        self.moved.borrow_mut().insert(block.id);
+        self.moved_since_layout.borrow_mut().insert(block.id);
+    }
+    /// Starts remembering the blocks changed from now on, see [`touch`](BlockList::touch) - Already remembered blocks stay as they are
+    pub fn start_touching(&mut self) {
+        self.touched.get_mut().get_or_insert_with(HashMap::new);
+    }
+    /// Stops remembering changed blocks and returns them, None if nothing was being remembered
+    pub fn take_touched(&mut self) -> Option<Touched> {
+        self.touched.get_mut().take()
+    }
+    /// Call before changing the position or links of a block of this list or the literals in its inputs, an open edit remembers how it was then
+    ///
+    /// Only the first call for a block during an edit copies it
+    pub fn touch(&self, block: &Block) {
+        if let Some(touched) = self.touched.borrow_mut().as_mut() {
+            touched
+                .entry(block.id)
+                .or_insert_with(|| self.index_of(&block.id).map(|x| (x, block.clone())));
+        }
+    }
+    /// A block that is new to an open edit, so undoing it removes it again
+    fn touch_added(&mut self, id: ID) {
+        if let Some(touched) = self.touched.get_mut() {
+            touched.entry(id).or_insert(None);
+        }
+    }
+    /// Ids of the blocks moved, resized or added since the last call, the stacks they are in are the ones to lay out again
+    pub fn take_moved_since_layout(&mut self) -> Vec<ID> {
+        std::mem::take(self.moved_since_layout.get_mut()).into_iter().collect()
+    }
+    /// Catches the spatial index up with the blocks moved or resized since the last call, only those are looked at
+    pub fn refresh_spatial_index(&mut self) {
+        for id in std::mem::take(self.moved.get_mut()) {
+            if let Some(idx) = self.index_of(&id) {
+                self.grid.update(&self.blocks[idx]);
+            }
+        }
+    }
+    /// Indexes of the blocks that may overlap the area between the corners (world coordinates), in drawing order
+    ///
+    /// Only narrows things down, the caller still checks each block
+    pub fn indexes_in_area(&self, min: (isize, isize), max: (isize, isize)) -> Vec<usize> {
+        let mut found: Vec<usize> = self
+            .grid
+            .query(min, max)
+            .iter()
+            .filter_map(|x| self.index_of(x))
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+    /// Same as [`indexes_in_area`](BlockList::indexes_in_area) with the area reaching `reach` past the point in every direction
+    pub fn indexes_near(&self, point: (isize, isize), reach: isize) -> Vec<usize> {
+        self.indexes_in_area(
+            (point.0 - reach, point.1 - reach),
+            (point.0 + reach, point.1 + reach),
+        )
+    }
+    /// Height of the tallest block, for queries reaching below blocks
+    pub fn tallest(&self) -> isize {
+        self.grid.tallest()
+    }
+    /// Mutable access for changes that keep every id as it is, like renaming
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Block> {
+        self.blocks.iter_mut()
+    }
+    fn reindex(&mut self, range: std::ops::Range<usize>) {
+        for idx in range {
+            self.indexes.insert(self.blocks[idx].id, idx);
+        }
+    }
+}
+
+impl std::ops::Deref for BlockList {
+    type Target = [Block];
+    fn deref(&self) -> &Self::Target {
+        &self.blocks
+    }
+}
+
+impl FromIterator<Block> for BlockList {
+    fn from_iter<I: IntoIterator<Item = Block>>(iter: I) -> Self {
+        let mut list = Self::new();
+        for block in iter {
+            list.push(block);
+        }
+        list
+    }
+}
+
+impl IntoIterator for BlockList {
+    type Item = Block;
+    type IntoIter = std::vec::IntoIter<Block>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a BlockList {
+    type Item = &'a Block;
+    type IntoIter = std::slice::Iter<'a, Block>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::WorkSpace;
+    use crate::logic::{LogicFast, Physics};
+    use crate::test_support::{add_block, font, load_example};
+
+    fn assert_indexed(blocks: &BlockList, after: &str) {
+        assert_eq!(blocks.indexes.len(), blocks.len(), "after {}", after);
+        for (idx, block) in blocks.iter().enumerate() {
+            assert_eq!(blocks.index_of(&block.id), Some(idx), "after {}", after);
+        }
+    }
+
+    #[test]
+    fn indexes_follow_every_change() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        for name in ["say_message", "wait_ticks", "repeat", "note", "add_numbers", "join_text", "if_else"] {
+            add_block(&mut workspace, &loaded, &names, &font, name);
+        }
+        let blocks = &mut workspace.blocks;
+        assert_indexed(blocks, "push");
+
+        let last = blocks.remove(blocks.len() - 1);
+        let middle = blocks.remove(2);
+        assert_indexed(blocks, "remove");
+        assert_eq!(blocks.index_of(&last.id), None);
+        blocks.insert(0, last);
+        blocks.insert(3, middle);
+        assert_indexed(blocks, "insert");
+
+        let replacement = blocks.remove(blocks.len() - 1);
+        let replaced = blocks.replace(1, replacement);
+        assert_indexed(blocks, "replace");
+        assert_eq!(blocks.index_of(&replaced.id), None);
+
+        blocks.move_to(blocks.len() - 1, 0);
+        assert_indexed(blocks, "move_to the front");
+        blocks.move_to(1, 4);
+        assert_indexed(blocks, "move_to further back");
+
+        let dropped = blocks[2].id;
+        blocks.retain(|x| x.id != dropped);
+        assert_indexed(blocks, "retain");
+        assert_eq!(blocks.index_of(&dropped), None);
+    }
+}
@@ -1,8 +1,17 @@
-use crate::CoordinateType;
+use crate::{CoordinateType, SizeType};
 
+/// How far the camera can zoom out and in, as screen pixels per world pixel
+pub const MIN_ZOOM: f64 = 0.25;
+pub const MAX_ZOOM: f64 = 4.0;
+/// Zoom factor of one step of the mouse wheel
+pub const ZOOM_STEP: f64 = 1.1;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Camera {
+    /// World position of the top left corner of the screen
     pub x: isize,
     pub y: isize,
+    /// Zoom, read it through [`Camera::zoom`] which keeps it within the limits
     pub z: f64,
 }
 
@@ -14,4 +23,47 @@ impl Camera {
             z: 1.0,
         }
     }
+    /// Screen pixels per world pixel, saves from before the limits existed may be outside of them
+    pub fn zoom(&self) -> f64 {
+        if self.z.is_finite() {
+            self.z.clamp(MIN_ZOOM, MAX_ZOOM)
+        } else {
+            1.0
+        }
+    }
+    /// Where a world position ends up on the screen
+    ///
+    /// Relative to the camera first, world coordinates are too large for exact floats
+    pub fn to_screen(&self, world: (isize, isize)) -> (isize, isize) {
+        (
+            ((world.0 - self.x) as f64 * self.zoom()).round() as isize,
+            ((world.1 - self.y) as f64 * self.zoom()).round() as isize,
+        )
+    }
+    /// The world position shown at a screen position, like the one of the mouse
+    pub fn to_world(&self, screen: (isize, isize)) -> (isize, isize) {
+        (
+            self.x + (screen.0 as f64 / self.zoom()).round() as isize,
+            self.y + (screen.1 as f64 / self.zoom()).round() as isize,
+        )
+    }
+    /// Length on the screen of a length in the world
+    pub fn scale(&self, length: SizeType) -> isize {
+        (length as f64 * self.zoom()).round() as isize
+    }
+    /// Length in the world of a length on the screen
+    pub fn unscale(&self, length: isize) -> isize {
+        (length as f64 / self.zoom()).round() as isize
+    }
+    /// Size of text drawn at `size` in the world
+    pub fn scale_text(&self, size: f32) -> f32 {
+        size * self.zoom() as f32
+    }
+    /// Multiplies the zoom by `factor` while keeping what is at `screen` in place
+    pub fn zoom_at(&mut self, factor: f64, screen: (isize, isize)) {
+        let anchor = self.to_world(screen);
+        self.z = (self.zoom() * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.x = anchor.0 - (screen.0 as f64 / self.zoom()).round() as isize;
+        self.y = anchor.1 - (screen.1 as f64 / self.zoom()).round() as isize;
+    }
 }
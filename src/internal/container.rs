@@ -0,0 +1,291 @@
+use crate::all::get_ids_connected_below_block;
+use crate::all::index_by_block_id;
+use crate::internal::{Block, BlockList, ID};
+use crate::CoordinateType;
+use crate::SizeType;
+
+/// Width of the left side of a container, the stacks in its slots start right of it
+pub const CONTAINER_ARM_WIDTH: SizeType = 16.0;
+/// Height of the bar below each slot, the bars between slots show the name of the next one
+pub const CONTAINER_BAR_HEIGHT: SizeType = 20.0;
+/// Height of a slot without any blocks in it
+pub const EMPTY_SLOT_HEIGHT: SizeType = 24.0;
+
+/// A named place in a container holding its own stack, like the "do" and "else" of an if/else block
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementSlot {
+    /// Internal name, also the translation key of the label and the name in the generated code
+    pub name: String,
+    /// Translated name shown on the bar above the slot
+    pub label: String,
+}
+
+impl StatementSlot {
+    pub fn new(name: String) -> Self {
+        Self {
+            label: name.clone(),
+            name,
+        }
+    }
+}
+
+impl Block {
+    /// Makes this block a container with these slots, all of them empty
+    pub fn set_slots(&mut self, slots: Vec<StatementSlot>) {
+        self.stored_slots = vec![None; slots.len()].into();
+        self.slot_heights = vec![EMPTY_SLOT_HEIGHT; slots.len()].into();
+        self.slots = slots;
+    }
+    pub fn is_container(&self) -> bool {
+        !self.slots.is_empty()
+    }
+    /// Height including the slots and their bars, what the next block of the stack goes below
+    pub fn full_height(&self) -> SizeType {
+        self.height.get()
+            + self
+                .slot_heights
+                .borrow()
+                .iter()
+                .map(|x| x + CONTAINER_BAR_HEIGHT)
+                .sum::<SizeType>()
+    }
+    /// Where the stack of `slot` starts, relative to the top of this block
+    pub fn slot_offset_y(&self, slot: usize) -> SizeType {
+        self.height.get()
+            + self.slot_heights.borrow()[..slot]
+                .iter()
+                .map(|x| x + CONTAINER_BAR_HEIGHT)
+                .sum::<SizeType>()
+    }
+    /// What of this block can be clicked as (x, y, width, height) relative to its top left corner
+    ///
+    /// Containers are shaped like a C, the inside of their slots belongs to the blocks in there or the background
+    pub fn hit_rectangles(&self) -> Vec<(SizeType, SizeType, SizeType, SizeType)> {
+        let width = self.width.get();
+        let height = self.height.get();
+        let mut rectangles = vec![(0.0, 0.0, width, height)];
+        if self.is_container() {
+            rectangles.push((0.0, height, CONTAINER_ARM_WIDTH, self.full_height() - height));
+            for slot in 0..self.slots.len() {
+                let bar_y = self.slot_offset_y(slot + 1) - CONTAINER_BAR_HEIGHT;
+                rectangles.push((0.0, bar_y, width, CONTAINER_BAR_HEIGHT));
+            }
+        }
+        rectangles
+    }
+    /// Stacks nothing else is connected above or holds in a slot
+    pub fn is_stack_root(&self) -> bool {
+        self.kind.is_stackable()
+            && self.connected_above.get().is_none()
+            && self.in_slot.get().is_none()
+    }
+    /// Moves every block below this one under it and the stacks in containers into their slots, growing the containers to fit
+    pub fn layout_stack(&self, blocks: &BlockList) {
+        let mut y = self.y.get();
+        for id in get_ids_connected_below_block(self.id, blocks, &mut Vec::new()) {
+            let Some(idx) = index_by_block_id(&id, blocks) else {
+                continue;
+            };
+            let block = &blocks[idx];
+            blocks.set_position(block, self.x.get(), y);
+            block.layout_slots(blocks);
+            y += block.full_height() as CoordinateType;
+        }
+    }
+    fn layout_slots(&self, blocks: &BlockList) {
+        for (slot, head) in self.stored_slots.borrow().iter().enumerate() {
+            let height = match head.and_then(|x| index_by_block_id(&x, blocks)) {
+                Some(idx) => {
+                    let head = &blocks[idx];
+                    blocks.set_position(
+                        head,
+                        self.x.get() + CONTAINER_ARM_WIDTH as CoordinateType,
+                        self.y.get() + self.slot_offset_y(slot) as CoordinateType,
+                    );
+                    head.layout_stack(blocks);
+                    get_ids_connected_below_block(head.id, blocks, &mut Vec::new())
+                        .iter()
+                        .filter_map(|x| index_by_block_id(x, blocks))
+                        .map(|x| blocks[x].full_height())
+                        .sum()
+                }
+                None => EMPTY_SLOT_HEIGHT,
+            };
+            let previous = std::mem::replace(&mut self.slot_heights.borrow_mut()[slot], height);
+            if previous != height {
+                blocks.mark_moved(self);
+            }
+        }
+    }
+    /// Puts the stack starting at this block into `slot` of the container, in front of what was in there
+    pub fn attach_to_slot(&self, container_id: ID, slot: usize, blocks: &BlockList) -> bool {
+        if !self.kind.can_connect_above() || self.connected_above.get().is_some() {
+            return false;
+        }
+        let Some(container_idx) = index_by_block_id(&container_id, blocks) else {
+            return false;
+        };
+        let container = &blocks[container_idx];
+        blocks.touch(container);
+        blocks.touch(self);
+        let previous = {
+            let mut stored_slots = container.stored_slots.borrow_mut();
+            let Some(stored) = stored_slots.get_mut(slot) else {
+                return false;
+            };
+            stored.replace(self.id)
+        };
+        if let Some(previous_idx) = previous.and_then(|x| index_by_block_id(&x, blocks)) {
+            let previous = &blocks[previous_idx];
+            blocks.touch(previous);
+            previous.in_slot.set(None);
+            let stack = get_ids_connected_below_block(self.id, blocks, &mut Vec::new());
+            if let Some(bottom_idx) = stack.last().and_then(|x| index_by_block_id(x, blocks)) {
+                blocks.touch(&blocks[bottom_idx]);
+                blocks[bottom_idx].connected_below.set(Some(previous.id));
+                previous.connected_above.set(Some(blocks[bottom_idx].id));
+            }
+        }
+        self.in_slot.set(Some((container_id, slot)));
+        self.recursive_set_topmost(blocks, self.id, true);
+        blocks.mark_moved(container);
+        true
+    }
+    /// Takes this block out of the slot it heads - With `single` only this block, the one below it heads the slot then
+    pub fn detach_from_slot(&self, blocks: &BlockList, single: bool) {
+        let Some((container_id, slot)) = self.in_slot.get() else {
+            return;
+        };
+        blocks.touch(self);
+        self.in_slot.set(None);
+        let below = self
+            .connected_below
+            .get()
+            .filter(|_| single)
+            .and_then(|x| index_by_block_id(&x, blocks));
+        if let Some(below_idx) = below {
+            let below = &blocks[below_idx];
+            blocks.touch(below);
+            self.connected_below.set(None);
+            below.connected_above.set(None);
+            below.in_slot.set(Some((container_id, slot)));
+            below.recursive_set_topmost(blocks, below.id, true);
+        }
+        if let Some(container_idx) = index_by_block_id(&container_id, blocks) {
+            let container = &blocks[container_idx];
+            blocks.touch(container);
+            if let Some(stored) = container.stored_slots.borrow_mut().get_mut(slot) {
+                *stored = below.map(|x| blocks[x].id);
+            }
+            blocks.mark_moved(container);
+        }
+        self.recursive_set_topmost(blocks, self.id, true);
+    }
+    /// Every block in the slots of this block and of the containers in there
+    pub fn get_all_ids_in_slots(&self, blocks: &BlockList) -> Vec<ID> {
+        let mut found = Vec::new();
+        for head in self.stored_slots.borrow().iter().flatten() {
+            for id in get_ids_connected_below_block(*head, blocks, &mut Vec::new()) {
+                found.push(id);
+                if let Some(idx) = index_by_block_id(&id, blocks) {
+                    found.extend(blocks[idx].get_all_ids_in_slots(blocks));
+                }
+            }
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::all::{get_block_id_under_point, handle_and_render_action_blocks_on_screen};
+    use crate::internal::WorkSpace;
+    use crate::logic::{LogicFast, Physics};
+    use crate::test_support::{add_block, font, load_example, stack_below};
+
+    #[test]
+    fn containers_are_hit_only_on_their_c_shape() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let if_else = add_block(&mut workspace, &loaded, &names, &font, "if_else");
+        let say = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&say).unwrap().attach_to_slot(if_else, 0, blocks);
+        let container = blocks.get_by_id(&if_else).unwrap();
+        container.layout_stack(blocks);
+        // Dropped last, so in front
+        let say_idx = blocks.index_of(&say).unwrap();
+        workspace.blocks.move_to(say_idx, 0);
+        workspace.blocks.refresh_spatial_index();
+
+        let blocks = &workspace.blocks;
+        let container = blocks.get_by_id(&if_else).unwrap();
+        let origin = (container.x.get() as isize, container.y.get() as isize);
+        let height = container.height.get() as isize;
+        let arm = CONTAINER_ARM_WIDTH as isize;
+        let hit = |x: isize, y: isize| {
+            get_block_id_under_point(blocks, origin.0 + x, origin.1 + y, &logic).map(|idx| blocks[idx].id)
+        };
+        assert_eq!(hit(5, 5), Some(if_else), "header");
+        assert_eq!(hit(5, height + 5), Some(if_else), "arm");
+        assert_eq!(hit(arm + 5, height + 5), Some(say), "block in the first slot");
+        let bar_y = container.slot_offset_y(1) as isize - 5;
+        assert_eq!(hit(container.width.get() as isize - 5, bar_y), Some(if_else), "bar");
+        assert_eq!(hit(arm + 5, container.slot_offset_y(1) as isize + 5), None, "empty second slot");
+        assert_eq!(hit(5, container.full_height() as isize + 5), None, "below");
+    }
+
+    /// Draws a frame, which only lays out the stacks something moved in, and checks nothing is where laying out everything wouldn't put it
+    fn assert_laid_out(workspace: &mut WorkSpace<LogicFast>, font: &fontdue::Font, after: &str) {
+        let buffer = mirl::platform::Buffer::new_empty(100, 100);
+        workspace.blocks.refresh_spatial_index();
+        let camera = workspace.camera.clone();
+        let colors = vec![0; workspace.blocks.iter().map(|x| x.block_color_id + 1).max().unwrap_or_default()];
+        handle_and_render_action_blocks_on_screen(&buffer, &camera, &mut workspace.blocks, &colors, font, workspace.logic);
+        let positions = |blocks: &BlockList| -> Vec<(ID, CoordinateType, CoordinateType)> {
+            blocks.iter().map(|x| (x.id, x.x.get(), x.y.get())).collect()
+        };
+        let incremental = positions(&workspace.blocks);
+        for block in workspace.blocks.iter().filter(|x| x.is_stack_root()) {
+            block.layout_stack(&workspace.blocks);
+        }
+        assert_eq!(incremental, positions(&workspace.blocks), "after {}", after);
+    }
+
+    #[test]
+    fn frames_lay_out_the_stacks_that_changed() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let event = add_block(&mut workspace, &loaded, &names, &font, "on_player_join");
+        let repeat = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let last = add_block(&mut workspace, &loaded, &names, &font, "wait_ticks");
+        let if_else = add_block(&mut workspace, &loaded, &names, &font, "if_else");
+        let first = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let second = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        stack_below(&workspace, event, repeat);
+        stack_below(&workspace, repeat, last);
+        assert_laid_out(&mut workspace, &font, "adding blocks");
+
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&if_else).unwrap().attach_to_slot(repeat, 0, blocks);
+        assert_laid_out(&mut workspace, &font, "dropping a container into a slot");
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&first).unwrap().attach_to_slot(if_else, 1, blocks);
+        assert_laid_out(&mut workspace, &font, "dropping into a nested slot");
+        stack_below(&workspace, first, second);
+        assert_laid_out(&mut workspace, &font, "stacking inside a slot");
+        let blocks = &workspace.blocks;
+        let dragged = blocks.get_by_id(&second).unwrap();
+        dragged.disconnect_above(blocks);
+        dragged.update_topmost(blocks, true);
+        assert_laid_out(&mut workspace, &font, "dragging the bottom of a stack out of a slot");
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&if_else).unwrap().detach_from_slot(blocks, false);
+        assert_laid_out(&mut workspace, &font, "dragging a container out of a slot");
+    }
+}
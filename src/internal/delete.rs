@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+
+use fontdue::Font;
+
+use crate::all::get_ids_connected_below_block;
+use crate::all::index_by_block_id;
+use crate::internal::{Block, BlockList, WorkSpace, ID};
+use crate::logic::Physics;
+
+impl<L: Physics> WorkSpace<'_, L> {
+    /// Removes the block at `idx` - Alone when `single`, otherwise together with everything connected below it
+    ///
+    /// Blocks stored in inputs and the stacks in container slots go with it, every link to a removed block is cleared afterwards.
+    /// Returns the ids of the removed blocks
+    pub fn delete_structure(&mut self, idx: usize, single: bool, font: &Font) -> Vec<ID> {
+        let block = &self.blocks[idx];
+        // Take it out of where it is like dragging it away would, so the blocks around it close the gap
+        if block.stored_in.get().is_some() {
+            block.detach_from_input(&self.blocks, font);
+        } else if block.in_slot.get().is_some() {
+            block.detach_from_slot(&self.blocks, single);
+        } else if single {
+            block.connect_below_to_above(&self.blocks);
+        } else {
+            block.disconnect_above(&self.blocks);
+        }
+        let stack = if single {
+            Vec::from([block.id])
+        } else {
+            get_ids_connected_below_block(block.id, &self.blocks, &mut Vec::new())
+        };
+
+        let mut removed: HashSet<ID> = HashSet::new();
+        for id in stack {
+            self.collect_contents(id, &mut removed);
+        }
+        self.blocks.retain(|x| !removed.contains(&x.id));
+        for block in self.blocks.iter() {
+            block.forget_links_to(&removed, &self.blocks);
+        }
+        // Stacks may have been cut where a removed block was
+        for block in self.blocks.iter() {
+            if block.connected_above.get().is_none() {
+                block.recursive_set_topmost(&self.blocks, block.id, true);
+            }
+        }
+        for block in self.blocks.iter() {
+            if block.stored_in.get().is_none() {
+                block.relayout_with_inputs(&self.blocks, font);
+                block.position_stored_inputs(&self.blocks);
+            }
+        }
+        removed.into_iter().collect()
+    }
+    /// `id` and everything in its inputs and slots, however deeply nested
+    fn collect_contents(&self, id: ID, found: &mut HashSet<ID>) {
+        let Some(idx) = index_by_block_id(&id, &self.blocks) else {
+            return;
+        };
+        if !found.insert(id) {
+            return;
+        }
+        let block = &self.blocks[idx];
+        let mut contents = block.get_all_ids_of_all_inputs(&self.blocks);
+        contents.extend(block.get_all_ids_in_slots(&self.blocks));
+        for content in contents {
+            self.collect_contents(content, found);
+        }
+    }
+}
+
+impl Block {
+    /// Clears every link pointing at one of the `removed` blocks, `blocks` is the list this block is in
+    pub fn forget_links_to(&self, removed: &HashSet<ID>, blocks: &BlockList) {
+        let links_removed = [
+            self.connected_top.get(),
+            self.connected_above.get(),
+            self.connected_below.get(),
+            self.stored_in.get().map(|x| x.0),
+            self.in_slot.get().map(|x| x.0),
+        ]
+        .into_iter()
+        .chain(self.stored_inputs.borrow().iter().copied())
+        .chain(self.stored_slots.borrow().iter().copied())
+        .flatten()
+        .any(|x| removed.contains(&x));
+        if links_removed {
+            blocks.touch(self);
+        }
+        let keep = |id: Option<ID>| id.filter(|x| !removed.contains(x));
+        let keep_pair = |pair: Option<(ID, usize)>| pair.filter(|x| !removed.contains(&x.0));
+        self.connected_top.set(keep(self.connected_top.get()));
+        self.connected_above.set(keep(self.connected_above.get()));
+        self.connected_below.set(keep(self.connected_below.get()));
+        self.possible_connection_above
+            .set(keep(self.possible_connection_above.get()));
+        self.possible_connection_below
+            .set(keep(self.possible_connection_below.get()));
+        self.stored_in.set(keep_pair(self.stored_in.get()));
+        self.in_slot.set(keep_pair(self.in_slot.get()));
+        self.possible_input.set(keep_pair(self.possible_input.get()));
+        self.possible_slot.set(keep_pair(self.possible_slot.get()));
+        for stored in self.stored_inputs.borrow_mut().iter_mut() {
+            *stored = keep(*stored);
+        }
+        for stored in self.stored_slots.borrow_mut().iter_mut() {
+            *stored = keep(*stored);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use fontdue::Font;
+use mirl::platform::KeyCode;
+
+use crate::all::index_by_block_id;
+use crate::internal::block_input::LITERAL_PADDING;
+use crate::internal::literal_editor::typed_text;
+use crate::internal::{Block, BlockInput, BlockList, ID};
+use crate::SizeType;
+
+/// What the dropdown wants to happen after handling the keys of a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DropdownOutcome {
+    /// Keep the list open
+    Open,
+    /// Store this allowed literal and close the list
+    Chosen(String),
+    /// Close the list without changing anything
+    Closed,
+}
+
+/// Where the open list is drawn, in world coordinates - The first row shows the filter, the options follow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DropdownLayout {
+    pub x: isize,
+    pub y: isize,
+    pub width: SizeType,
+    pub row_height: SizeType,
+    pub font_size: f32,
+}
+
+/// The open option list of one dropdown input
+///
+/// Typing filters the options by their label, the arrow keys move the highlight and Enter picks it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dropdown {
+    /// Block owning the input
+    pub block: ID,
+    pub input_idx: usize,
+    pub filter: String,
+    /// Index into the options left after filtering
+    highlighted: usize,
+    /// Keys down last frame, only keys going down do anything
+    last_keys: Vec<KeyCode>,
+}
+
+impl Dropdown {
+    /// Opens the list of input `input_idx` of `block` with the current option highlighted
+    ///
+    /// `keys_down` are ignored until released so the keys held while clicking don't filter anything
+    pub fn new(block: &Block, input_idx: usize, keys_down: Vec<KeyCode>) -> Self {
+        let current = block.stored_literals.borrow().get(input_idx).cloned().flatten();
+        let highlighted = block
+            .inputs
+            .get(input_idx)
+            .and_then(|input| input.options().position(|x| Some(x.0) == current.as_deref()))
+            .unwrap_or_default();
+        Self {
+            block: block.id,
+            input_idx,
+            filter: String::new(),
+            highlighted,
+            last_keys: keys_down,
+        }
+    }
+    pub fn highlighted(&self) -> usize {
+        self.highlighted
+    }
+    /// The block and input this list belongs to, None if the block is gone
+    pub fn input<'a>(&self, blocks: &'a BlockList) -> Option<(&'a Block, &'a BlockInput)> {
+        let block = &blocks[index_by_block_id(&self.block, blocks)?];
+        Some((block, block.inputs.get(self.input_idx)?))
+    }
+    /// Allowed literal and label of every option whose label contains the filter, ignoring case
+    pub fn visible_options<'a>(&self, input: &'a BlockInput) -> Vec<(&'a str, &'a str)> {
+        let filter = self.filter.to_lowercase();
+        input
+            .options()
+            .filter(|x| x.1.to_lowercase().contains(&filter))
+            .collect()
+    }
+    /// Filters, moves the highlight or picks an option for every key that went down since the last frame
+    pub fn handle_keys(
+        &mut self,
+        keys_down: Vec<KeyCode>,
+        shift: bool,
+        control: bool,
+        input: &BlockInput,
+    ) -> DropdownOutcome {
+        let pressed: Vec<KeyCode> = keys_down
+            .iter()
+            .filter(|x| !self.last_keys.contains(x))
+            .copied()
+            .collect();
+        self.last_keys = keys_down;
+
+        for key in pressed {
+            let visible = self.visible_options(input).len();
+            match key {
+                KeyCode::Enter | KeyCode::KeyPadEnter | KeyCode::Tab => {
+                    return match self.visible_options(input).get(self.highlighted) {
+                        Some(chosen) => DropdownOutcome::Chosen(chosen.0.to_string()),
+                        None => DropdownOutcome::Closed,
+                    };
+                }
+                KeyCode::Escape => return DropdownOutcome::Closed,
+                KeyCode::Up => self.highlighted = self.highlighted.saturating_sub(1),
+                KeyCode::Down => {
+                    self.highlighted = (self.highlighted + 1).min(visible.saturating_sub(1))
+                }
+                KeyCode::Home | KeyCode::PageUp => self.highlighted = 0,
+                KeyCode::End | KeyCode::PageDown => self.highlighted = visible.saturating_sub(1),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.highlighted = 0;
+                }
+                _ if control => {}
+                _ => {
+                    if let Some(typed) = typed_text(key, shift) {
+                        self.filter.push_str(&typed);
+                        self.highlighted = 0;
+                    }
+                }
+            }
+        }
+        DropdownOutcome::Open
+    }
+    /// Below the input, as wide as the input or the longest label
+    pub fn layout(&self, blocks: &BlockList, font: &Font) -> Option<DropdownLayout> {
+        let (block, input) = self.input(blocks)?;
+        let offsets = block.input_offsets.borrow();
+        let start = *offsets.get(self.input_idx * 2 + 1)?;
+        let end = *offsets.get(self.input_idx * 2 + 2)?;
+        let font_size = (block.height.get() / 2.0) as f32;
+        let widest = input
+            .options()
+            .map(|x| mirl::render::get_length_of_string(x.1, font_size, font) as SizeType)
+            .fold(0.0, SizeType::max);
+        Some(DropdownLayout {
+            x: block.x.get() as isize + start as isize,
+            y: block.y.get() as isize + block.height.get() as isize,
+            width: (end - start).max(widest + LITERAL_PADDING * 2.0),
+            row_height: font_size as SizeType + LITERAL_PADDING,
+            font_size,
+        })
+    }
+    /// Index into the visible options of the row at the world position, None outside of the option rows
+    pub fn option_at(
+        layout: &DropdownLayout,
+        visible: usize,
+        pos_x: isize,
+        pos_y: isize,
+    ) -> Option<usize> {
+        if !Self::contains(layout, visible, pos_x, pos_y) {
+            return None;
+        }
+        // Row 0 is the filter
+        let row = ((pos_y - layout.y) as SizeType / layout.row_height).floor();
+        if row < 1.0 {
+            return None;
+        }
+        Some(row as usize - 1).filter(|x| *x < visible)
+    }
+    /// Is the world position on the list, including its filter row
+    pub fn contains(layout: &DropdownLayout, visible: usize, pos_x: isize, pos_y: isize) -> bool {
+        let (relative_x, relative_y) = ((pos_x - layout.x) as SizeType, (pos_y - layout.y) as SizeType);
+        (0.0..=layout.width).contains(&relative_x)
+            && (0.0..=layout.row_height * (visible + 1) as SizeType).contains(&relative_y)
+    }
+}
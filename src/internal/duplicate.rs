@@ -0,0 +1,101 @@
+use fontdue::Font;
+
+use crate::all::get_ids_connected_below_block;
+use crate::all::index_by_block_id;
+use crate::internal::block::BlockError;
+use crate::internal::{WorkSpace, ID};
+use crate::logic::Physics;
+
+impl<L: Physics> WorkSpace<'_, L> {
+    /// Copies the block at `idx` - Alone when `single`, otherwise together with everything connected below it
+    ///
+    /// Inline blocks stored in inputs and the stacks in container slots are copied too, every copy gets a fresh id and only links to other copies.
+    /// Returns the index of the copy of the block at `idx`
+    pub fn duplicate_structure(
+        &mut self,
+        idx: usize,
+        single: bool,
+        output_color_names: &[String],
+        font: &Font,
+    ) -> Result<usize, BlockError> {
+        let copies =
+            self.duplicate_stack(self.blocks[idx].id, single, output_color_names, font)?;
+        let head_idx = index_by_block_id(&copies[0], &self.blocks)
+            .expect("Copies were just pushed");
+        self.blocks[head_idx].recursive_set_topmost(&self.blocks, copies[0], true);
+        Ok(head_idx)
+    }
+    /// Copies the stack starting at `head_id` (or only the head when `single`) and links the copies like the originals - Returns their ids from top to bottom
+    fn duplicate_stack(
+        &mut self,
+        head_id: ID,
+        single: bool,
+        output_color_names: &[String],
+        font: &Font,
+    ) -> Result<Vec<ID>, BlockError> {
+        let stack = if single {
+            Vec::from([head_id])
+        } else {
+            get_ids_connected_below_block(head_id, &self.blocks, &mut Vec::new())
+        };
+
+        let mut copies = Vec::new();
+        for id in &stack {
+            copies.push(self.duplicate_with_inputs(*id, output_color_names, font)?);
+        }
+
+        for (position, copy_id) in copies.iter().enumerate() {
+            let copy = &self.blocks[index_by_block_id(copy_id, &self.blocks)
+                .expect("Copies were just pushed")];
+            copy.connected_above
+                .set(position.checked_sub(1).map(|x| copies[x]));
+            copy.connected_below.set(copies.get(position + 1).copied());
+        }
+        Ok(copies)
+    }
+    /// Copies one block and, recursively, the blocks stored in its inputs and the stacks in its slots - Returns the id of the copy
+    fn duplicate_with_inputs(
+        &mut self,
+        id: ID,
+        output_color_names: &[String],
+        font: &Font,
+    ) -> Result<ID, BlockError> {
+        let original = self.blocks[index_by_block_id(&id, &self.blocks)
+            .expect("Only existing blocks are duplicated")]
+        .clone();
+        let copy = original.duplicate(output_color_names, font, self)?;
+        copy.stored_literals
+            .replace(original.stored_literals.borrow().clone());
+        let stored_inputs = original.stored_inputs.borrow().clone();
+        for (input_idx, stored) in stored_inputs.into_iter().enumerate() {
+            if let Some(stored) = stored {
+                let stored_copy =
+                    self.duplicate_with_inputs(stored, output_color_names, font)?;
+                copy.stored_inputs.borrow_mut()[input_idx] = Some(stored_copy);
+                let stored_copy_idx = index_by_block_id(&stored_copy, &self.blocks)
+                    .expect("Copies were just pushed");
+                self.blocks[stored_copy_idx]
+                    .stored_in
+                    .set(Some((copy.id, input_idx)));
+            }
+        }
+        let stored_slots = original.stored_slots.borrow().clone();
+        for (slot, head) in stored_slots.into_iter().enumerate() {
+            if let Some(head) = head {
+                let body = self.duplicate_stack(head, false, output_color_names, font)?;
+                copy.stored_slots.borrow_mut()[slot] = Some(body[0]);
+                let body_idx = index_by_block_id(&body[0], &self.blocks)
+                    .expect("Copies were just pushed");
+                let body_head = &self.blocks[body_idx];
+                body_head.in_slot.set(Some((copy.id, slot)));
+                body_head.recursive_set_topmost(&self.blocks, body_head.id, true);
+            }
+        }
+        let copy_id = copy.id;
+        self.blocks.push(copy);
+        let copy = self.blocks.last().expect("Copy was just pushed");
+        copy.relayout_with_inputs(&self.blocks, font);
+        copy.position_stored_inputs(&self.blocks);
+        Ok(copy_id)
+    }
+}
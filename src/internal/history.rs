@@ -0,0 +1,353 @@
+use fontdue::Font;
+
+use crate::all::index_by_block_id;
+use crate::internal::{Block, Camera, WorkSpace, ID};
+use crate::logic::Physics;
+use crate::CoordinateType;
+
+/// Steps kept per workspace, the oldest ones are forgotten first
+pub const MAX_HISTORY_STEPS: usize = 100;
+
+/// Undo/redo steps of one workspace
+///
+/// An edit is everything between [`WorkSpace::begin_edit`] and [`WorkSpace::end_edit`], like a whole drag from pressing to letting go
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    undo: Vec<Step>,
+    redo: Vec<Step>,
+    /// Where the camera was when the open edit began, the blocks it changes are remembered by [`BlockList::touch`](crate::internal::BlockList::touch)
+    pending: Option<Camera>,
+}
+
+/// One undoable edit, only the blocks it changed are kept
+#[derive(Debug, Clone)]
+struct Step {
+    changes: Vec<Change>,
+    /// Before and after, only if the edit moved the camera
+    camera: Option<(Camera, Camera)>,
+}
+
+/// A block before and after an edit together with its index - None if it didn't exist
+#[derive(Debug, Clone)]
+struct Change {
+    id: ID,
+    before: Option<(usize, Block)>,
+    after: Option<(usize, Block)>,
+}
+
+impl Change {
+    /// The block after the step when going `forward`, the one before it otherwise
+    fn target(&self, forward: bool) -> Option<&(usize, Block)> {
+        if forward {
+            self.after.as_ref()
+        } else {
+            self.before.as_ref()
+        }
+    }
+}
+
+/// The parts of a block edits change, the rest is either fixed or recalculated every frame
+#[derive(Debug, PartialEq)]
+struct BlockState {
+    x: CoordinateType,
+    y: CoordinateType,
+    connected_top: Option<ID>,
+    connected_above: Option<ID>,
+    connected_below: Option<ID>,
+    stored_inputs: Vec<Option<ID>>,
+    stored_literals: Vec<Option<String>>,
+    stored_in: Option<(ID, usize)>,
+    stored_slots: Vec<Option<ID>>,
+    in_slot: Option<(ID, usize)>,
+}
+
+impl BlockState {
+    fn of(block: &Block) -> Self {
+        Self {
+            x: block.x.get(),
+            y: block.y.get(),
+            connected_top: block.connected_top.get(),
+            connected_above: block.connected_above.get(),
+            connected_below: block.connected_below.get(),
+            stored_inputs: block.stored_inputs.borrow().clone(),
+            stored_literals: block.stored_literals.borrow().clone(),
+            stored_in: block.stored_in.get(),
+            stored_slots: block.stored_slots.borrow().clone(),
+            in_slot: block.in_slot.get(),
+        }
+    }
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+    /// Is an edit waiting for [`WorkSpace::end_edit`]
+    pub fn is_editing(&self) -> bool {
+        self.pending.is_some()
+    }
+    fn push(&mut self, step: Step) {
+        self.undo.push(step);
+        if self.undo.len() > MAX_HISTORY_STEPS {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+}
+
+impl<L: Physics> WorkSpace<'_, L> {
+    /// Starts remembering what changes so the next [`end_edit`](WorkSpace::end_edit) can record it - Does nothing while an edit is open, so nested edits become one step
+    ///
+    /// Blocks are only copied once something is about to change them
+    pub fn begin_edit(&mut self) {
+        if self.history.pending.is_none() {
+            self.history.pending = Some(self.camera.clone());
+            self.blocks.start_touching();
+        }
+    }
+    /// Records what changed since [`begin_edit`](WorkSpace::begin_edit) as one step, edits that changed nothing are dropped
+    pub fn end_edit(&mut self) {
+        let Some(camera_before) = self.history.pending.take() else {
+            return;
+        };
+        let mut changes = Vec::new();
+        for (id, before) in self.blocks.take_touched().unwrap_or_default() {
+            let after = index_by_block_id(&id, &self.blocks).map(|x| (x, self.blocks[x].clone()));
+            match (&before, &after) {
+                // Added and removed again
+                (None, None) => continue,
+                (Some((_, old)), Some((_, new))) if BlockState::of(old) == BlockState::of(new) => continue,
+                _ => {}
+            }
+            changes.push(Change { id, before, after });
+        }
+        // Same order every time
+        changes.sort_by_key(|x| x.id);
+        let camera = Some((camera_before, self.camera.clone())).filter(|x| x.0 != x.1);
+        if changes.is_empty() && camera.is_none() {
+            return;
+        }
+        self.history.push(Step { changes, camera });
+    }
+    /// Records moving the camera away from `before` outside of an edit, like scrolling or zooming with the mouse wheel
+    ///
+    /// Follows up on a step that only moved the camera too, so a whole scroll is one step instead of one per wheel tick
+    pub fn record_camera_move(&mut self, before: Camera) {
+        // The open edit records where the camera ends up itself
+        if self.history.is_editing() || before == self.camera {
+            return;
+        }
+        if let Some(last) = self.history.undo.last_mut().filter(|x| x.changes.is_empty()) {
+            if let Some((_, after)) = &mut last.camera {
+                *after = self.camera.clone();
+                self.history.redo.clear();
+                return;
+            }
+        }
+        self.history.push(Step {
+            changes: Vec::new(),
+            camera: Some((before, self.camera.clone())),
+        });
+    }
+    /// Puts back what the last step changed, returns false if there is nothing to undo
+    pub fn undo(&mut self, font: &Font) -> bool {
+        self.end_edit();
+        let Some(step) = self.history.undo.pop() else {
+            return false;
+        };
+        self.apply_step(&step, false, font);
+        self.history.redo.push(step);
+        true
+    }
+    /// Does the last undone step again, returns false if there is nothing to redo
+    pub fn redo(&mut self, font: &Font) -> bool {
+        self.end_edit();
+        let Some(step) = self.history.redo.pop() else {
+            return false;
+        };
+        self.apply_step(&step, true, font);
+        self.history.undo.push(step);
+        true
+    }
+    /// Replaces every changed block with its state from before (or after, when `forward`) the step
+    fn apply_step(&mut self, step: &Step, forward: bool, font: &Font) {
+        // Remove first so the indexes of the blocks put back in are the ones they had
+        for change in &step.changes {
+            if change.target(forward).is_none() {
+                if let Some(idx) = index_by_block_id(&change.id, &self.blocks) {
+                    self.blocks.remove(idx);
+                }
+            }
+        }
+        let mut inserted: Vec<&(usize, Block)> = step.changes.iter().filter_map(|x| x.target(forward)).collect();
+        inserted.sort_by_key(|x| x.0);
+        for (idx, block) in inserted {
+            match index_by_block_id(&block.id, &self.blocks) {
+                Some(existing) => {
+                    self.blocks.replace(existing, block.clone());
+                }
+                None => self.blocks.insert((*idx).min(self.blocks.len()), block.clone()),
+            }
+        }
+        if let Some((before, after)) = &step.camera {
+            self.camera = if forward { after } else { before }.clone();
+        }
+        // Literals may have changed, so the widths have too
+        for block in self.blocks.iter() {
+            if block.stored_in.get().is_none() {
+                block.relayout_with_inputs(&self.blocks, font);
+                block.position_stored_inputs(&self.blocks);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::LogicFast;
+    use crate::test_support::{add_block, font, load_example, stack_below};
+
+    /// Every block of the workspace as far as undoing is concerned, by id
+    fn states(workspace: &WorkSpace<LogicFast>) -> Vec<(ID, BlockState)> {
+        let mut states: Vec<(ID, BlockState)> = workspace.blocks.iter().map(|x| (x.id, BlockState::of(x))).collect();
+        states.sort_by_key(|x| x.0);
+        states
+    }
+
+    /// Lays everything out like drawing a frame does, edits are only over once that happened
+    fn lay_out(workspace: &WorkSpace<LogicFast>) {
+        let blocks = &workspace.blocks;
+        for block in blocks.iter().filter(|x| x.is_stack_root()) {
+            block.layout_stack(blocks);
+        }
+        for block in blocks.iter().filter(|x| x.stored_in.get().is_none()) {
+            block.position_stored_inputs(blocks);
+        }
+    }
+
+    /// Runs `change` as one edit and checks undoing and redoing it gets back exactly what was there before and after
+    fn assert_undoable(workspace: &mut WorkSpace<LogicFast>, font: &Font, change: impl FnOnce(&mut WorkSpace<LogicFast>)) {
+        lay_out(workspace);
+        let before = states(workspace);
+        workspace.begin_edit();
+        change(workspace);
+        lay_out(workspace);
+        workspace.end_edit();
+        let after = states(workspace);
+        assert_ne!(before, after, "the edit changed nothing");
+
+        assert!(workspace.undo(font));
+        assert_eq!(states(workspace), before);
+        assert_eq!(workspace.validate(), []);
+        assert!(workspace.redo(font));
+        assert_eq!(states(workspace), after);
+        assert_eq!(workspace.validate(), []);
+    }
+
+    #[test]
+    fn connecting_is_undone() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let event = add_block(&mut workspace, &loaded, &names, &font, "on_player_join");
+        let first = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let inserted = add_block(&mut workspace, &loaded, &names, &font, "wait_ticks");
+        stack_below(&workspace, event, first);
+
+        // Goes between the two
+        assert_undoable(&mut workspace, &font, |workspace| stack_below(workspace, event, inserted));
+    }
+
+    #[test]
+    fn slot_drop_is_undone() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let event = add_block(&mut workspace, &loaded, &names, &font, "on_player_join");
+        let repeat = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let dropped = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        stack_below(&workspace, event, repeat);
+
+        assert_undoable(&mut workspace, &font, |workspace| {
+            let blocks = &workspace.blocks;
+            assert!(blocks.get_by_id(&dropped).unwrap().attach_to_slot(repeat, 0, blocks));
+        });
+    }
+
+    #[test]
+    fn delete_is_undone() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let event = add_block(&mut workspace, &loaded, &names, &font, "on_player_join");
+        let repeat = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let last = add_block(&mut workspace, &loaded, &names, &font, "wait_ticks");
+        let inside = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let text = add_block(&mut workspace, &loaded, &names, &font, "join_text");
+        stack_below(&workspace, event, repeat);
+        stack_below(&workspace, repeat, last);
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&inside).unwrap().attach_to_slot(repeat, 0, blocks);
+        blocks.get_by_id(&text).unwrap().attach_to_input(inside, 0, blocks, &font);
+        blocks.get_by_id(&text).unwrap().set_literal(0, Some("hi".to_string()), blocks, &font);
+
+        // Takes the slot and the stored block with it, the block below closes the gap
+        assert_undoable(&mut workspace, &font, |workspace| {
+            let idx = workspace.blocks.index_of(&repeat).unwrap();
+            assert_eq!(workspace.delete_structure(idx, true, &font).len(), 3);
+        });
+    }
+
+    #[test]
+    fn duplicate_is_undone() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let repeat = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let below = add_block(&mut workspace, &loaded, &names, &font, "wait_ticks");
+        let inside = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        stack_below(&workspace, repeat, below);
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&inside).unwrap().attach_to_slot(repeat, 0, blocks);
+        let count = workspace.blocks.len();
+
+        assert_undoable(&mut workspace, &font, |workspace| {
+            let idx = workspace.blocks.index_of(&repeat).unwrap();
+            workspace.duplicate_structure(idx, false, &names, &font).unwrap();
+        });
+        assert_eq!(workspace.blocks.len(), count + 3);
+    }
+
+    #[test]
+    fn oldest_steps_are_forgotten() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let moved = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let mut remembered = Vec::new();
+        for step in 0..MAX_HISTORY_STEPS + 5 {
+            remembered.push(states(&workspace));
+            workspace.begin_edit();
+            let blocks = &workspace.blocks;
+            let block = blocks.get_by_id(&moved).unwrap();
+            blocks.set_position(block, block.x.get() + 10, block.y.get() + step as CoordinateType);
+            workspace.end_edit();
+        }
+
+        let mut undone = 0;
+        while workspace.undo(&font) {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_HISTORY_STEPS);
+        assert_eq!(states(&workspace), remembered[5]);
+    }
+}
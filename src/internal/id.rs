@@ -1,7 +1,19 @@
-use crate::internal::Block;
+use crate::internal::BlockList;
 
 #[repr(transparent)] // No clue what this means :)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(transparent)]
 pub struct ID(usize);
 
 impl From<usize> for ID {
@@ -33,11 +45,11 @@ impl std::fmt::Display for ID {
 
 
 pub trait UsizeGetID {
-    fn get_id_of_idx(self, blocks: &Vec<Block>) -> ID;
+    fn get_id_of_idx(self, blocks: &BlockList) -> ID;
 }
 
 impl UsizeGetID for usize {
-    fn get_id_of_idx(self, blocks: &Vec<Block>) -> ID {
+    fn get_id_of_idx(self, blocks: &BlockList) -> ID {
         blocks[self].id
     }
 }
@@ -0,0 +1,527 @@
+use std::collections::HashSet;
+
+use fontdue::Font;
+
+use crate::all::index_by_block_id;
+use crate::internal::{Block, BlockList, WorkSpace, ID};
+use crate::logic::Physics;
+
+/// One rule about how blocks link to each other that the workspace breaks
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntegrityIssue {
+    /// A link names a block that doesn't exist
+    DanglingLink {
+        block: ID,
+        link: &'static str,
+        target: ID,
+    },
+    /// `above` says `below` is below it or the other way around, but not both
+    OneSidedLink { above: ID, below: ID },
+    /// Following the blocks below, in inputs or in slots leads back to where it started, like a container holding itself
+    Cycle { blocks: Vec<ID> },
+    /// [`connected_top`](crate::internal::Block::connected_top) isn't the head of the stack the block is in
+    WrongTop {
+        block: ID,
+        found: Option<ID>,
+        expected: Option<ID>,
+    },
+    /// A block that can't be stacked there has something above or below it, like an inline block or an event with something above it
+    NotStackable { block: ID },
+    /// The input of `host` and the block stored in it disagree about where it is stored
+    StoredInputMismatch {
+        host: ID,
+        input_idx: usize,
+        stored: ID,
+    },
+    /// The slot of `container` and the head of the stack in it disagree about where it is
+    SlotMismatch {
+        container: ID,
+        slot: usize,
+        head: ID,
+    },
+}
+
+impl std::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IntegrityIssue::DanglingLink {
+                block,
+                link,
+                target,
+            } => write!(f, "{} of block {} names block {} which doesn't exist", link, block, target),
+            IntegrityIssue::OneSidedLink { above, below } => write!(
+                f,
+                "Blocks {} and {} disagree about being connected",
+                above, below
+            ),
+            IntegrityIssue::Cycle { blocks } => {
+                let ids: Vec<String> = blocks.iter().map(|x| x.to_string()).collect();
+                write!(f, "Blocks {} are connected or hold each other in a loop", ids.join(" -> "))
+            }
+            IntegrityIssue::WrongTop {
+                block,
+                found,
+                expected,
+            } => write!(
+                f,
+                "Block {} thinks the top of its stack is {:?} instead of {:?}",
+                block, found, expected
+            ),
+            IntegrityIssue::NotStackable { block } => write!(
+                f,
+                "Block {} is connected to a stack it can't be part of",
+                block
+            ),
+            IntegrityIssue::StoredInputMismatch {
+                host,
+                input_idx,
+                stored,
+            } => write!(
+                f,
+                "Input {} of block {} and block {} disagree about it being stored there",
+                input_idx, host, stored
+            ),
+            IntegrityIssue::SlotMismatch {
+                container,
+                slot,
+                head,
+            } => write!(
+                f,
+                "Slot {} of block {} and block {} disagree about it heading the slot",
+                slot, container, head
+            ),
+        }
+    }
+}
+
+impl<L: Physics> WorkSpace<'_, L> {
+    /// Every broken link between the blocks, an empty list means the workspace is consistent
+    pub fn validate(&self) -> Vec<IntegrityIssue> {
+        let blocks = &self.blocks;
+        let mut issues = Vec::new();
+        let exists = |id: &ID| index_by_block_id(id, blocks).is_some();
+
+        for block in blocks.iter() {
+            let mut links = Vec::from([
+                ("connected_top", block.connected_top.get()),
+                ("connected_above", block.connected_above.get()),
+                ("connected_below", block.connected_below.get()),
+                ("stored_in", block.stored_in.get().map(|x| x.0)),
+                ("in_slot", block.in_slot.get().map(|x| x.0)),
+            ]);
+            links.extend(block.stored_inputs.borrow().iter().map(|x| ("stored_inputs", *x)));
+            links.extend(block.stored_slots.borrow().iter().map(|x| ("stored_slots", *x)));
+            for (link, target) in links {
+                if let Some(target) = target.filter(|x| !exists(x)) {
+                    issues.push(IntegrityIssue::DanglingLink {
+                        block: block.id,
+                        link,
+                        target,
+                    });
+                }
+            }
+        }
+
+        for block in blocks.iter() {
+            let has_above = block.connected_above.get().is_some();
+            let has_below = block.connected_below.get().is_some();
+            if (has_above && !block.kind.can_connect_above()) || (has_below && !block.kind.is_stackable()) {
+                issues.push(IntegrityIssue::NotStackable { block: block.id });
+            }
+            if let Some(below) = block.connected_below.get().and_then(|x| index_by_block_id(&x, blocks)) {
+                if blocks[below].connected_above.get() != Some(block.id) {
+                    issues.push(IntegrityIssue::OneSidedLink {
+                        above: block.id,
+                        below: blocks[below].id,
+                    });
+                }
+            }
+            if let Some(above) = block.connected_above.get().and_then(|x| index_by_block_id(&x, blocks)) {
+                if blocks[above].connected_below.get() != Some(block.id) {
+                    issues.push(IntegrityIssue::OneSidedLink {
+                        above: blocks[above].id,
+                        below: block.id,
+                    });
+                }
+            }
+        }
+
+        issues.extend(find_cycles(blocks).into_iter().map(|blocks| IntegrityIssue::Cycle { blocks }));
+
+        // Walked down from the heads like recursive_set_topmost does, blocks in loops have no head to compare with
+        let mut visited = HashSet::new();
+        for head in blocks.iter() {
+            if head.connected_above.get().is_some_and(|x| index_by_block_id(&x, blocks).is_some()) {
+                continue;
+            }
+            let mut current = Some(head.id);
+            while let Some(idx) = current.and_then(|x| index_by_block_id(&x, blocks)) {
+                let block = &blocks[idx];
+                if !visited.insert(block.id) {
+                    break;
+                }
+                let expected = Some(head.id).filter(|x| *x != block.id);
+                if block.connected_top.get() != expected {
+                    issues.push(IntegrityIssue::WrongTop {
+                        block: block.id,
+                        found: block.connected_top.get(),
+                        expected,
+                    });
+                }
+                current = block.connected_below.get();
+            }
+        }
+
+        for host in blocks.iter() {
+            for (input_idx, stored) in host.stored_inputs.borrow().iter().enumerate() {
+                let Some(stored) = stored.and_then(|x| index_by_block_id(&x, blocks)) else {
+                    continue;
+                };
+                if blocks[stored].stored_in.get() != Some((host.id, input_idx)) {
+                    issues.push(IntegrityIssue::StoredInputMismatch {
+                        host: host.id,
+                        input_idx,
+                        stored: blocks[stored].id,
+                    });
+                }
+            }
+        }
+        for block in blocks.iter() {
+            let Some((host, input_idx)) = block.stored_in.get() else {
+                continue;
+            };
+            let Some(host_idx) = index_by_block_id(&host, blocks) else {
+                continue;
+            };
+            if blocks[host_idx].stored_inputs.borrow().get(input_idx).copied().flatten() != Some(block.id) {
+                issues.push(IntegrityIssue::StoredInputMismatch {
+                    host,
+                    input_idx,
+                    stored: block.id,
+                });
+            }
+        }
+
+        for container in blocks.iter() {
+            for (slot, head) in container.stored_slots.borrow().iter().enumerate() {
+                let Some(head) = head.and_then(|x| index_by_block_id(&x, blocks)) else {
+                    continue;
+                };
+                let head = &blocks[head];
+                if head.in_slot.get() != Some((container.id, slot)) || head.connected_above.get().is_some() {
+                    issues.push(IntegrityIssue::SlotMismatch {
+                        container: container.id,
+                        slot,
+                        head: head.id,
+                    });
+                }
+            }
+        }
+        for block in blocks.iter() {
+            let Some((container, slot)) = block.in_slot.get() else {
+                continue;
+            };
+            let Some(container_idx) = index_by_block_id(&container, blocks) else {
+                continue;
+            };
+            if blocks[container_idx].stored_slots.borrow().get(slot).copied().flatten() != Some(block.id) {
+                issues.push(IntegrityIssue::SlotMismatch {
+                    container,
+                    slot,
+                    head: block.id,
+                });
+            }
+        }
+        issues
+    }
+    /// Fixes everything [`validate`](WorkSpace::validate) finds and returns what that was
+    ///
+    /// Links only one side agrees with are completed if the other side is free and dropped otherwise, loops are cut at the link leading back to their first block
+    pub fn repair(&mut self, font: &Font) -> Vec<IntegrityIssue> {
+        let issues = self.validate();
+        if issues.is_empty() {
+            return issues;
+        }
+        let blocks = &self.blocks;
+        // Rare enough to remember every block for an open edit instead of each one a fix touches
+        for block in blocks.iter() {
+            blocks.touch(block);
+        }
+        let existing: HashSet<ID> = blocks.iter().map(|x| x.id).collect();
+        for block in blocks.iter() {
+            block.forget_links_to_missing(&existing, blocks);
+        }
+
+        for block in blocks.iter() {
+            if !block.kind.can_connect_above() {
+                block.connected_above.set(None);
+            }
+            if !block.kind.is_stackable() {
+                block.connected_below.set(None);
+            }
+        }
+        // Complete links whose other side is free, drop the rest
+        for block in blocks.iter() {
+            let Some(below) = block.connected_below.get().and_then(|x| index_by_block_id(&x, blocks)) else {
+                continue;
+            };
+            let below = &blocks[below];
+            match below.connected_above.get() {
+                Some(above) if above == block.id => {}
+                None if below.kind.can_connect_above() => below.connected_above.set(Some(block.id)),
+                _ => block.connected_below.set(None),
+            }
+        }
+        for block in blocks.iter() {
+            let Some(above) = block.connected_above.get().and_then(|x| index_by_block_id(&x, blocks)) else {
+                continue;
+            };
+            if blocks[above].connected_below.get() != Some(block.id) {
+                block.connected_above.set(None);
+            }
+        }
+        // Cutting one loop may leave another one sharing blocks with it, each round cuts at least one link
+        loop {
+            let cycles = find_cycles(blocks);
+            if cycles.is_empty() {
+                break;
+            }
+            for cycle in cycles {
+                let (Some(first), Some(last)) = (
+                    cycle.first().and_then(|x| index_by_block_id(x, blocks)),
+                    cycle.last().and_then(|x| index_by_block_id(x, blocks)),
+                ) else {
+                    continue;
+                };
+                cut_link(&blocks[last], &blocks[first]);
+            }
+        }
+
+        // Where the two sides disagree, the side naming the other wins if the other is free
+        for host in blocks.iter() {
+            for (input_idx, stored) in host.stored_inputs.borrow_mut().iter_mut().enumerate() {
+                let Some(stored_idx) = stored.and_then(|x| index_by_block_id(&x, blocks)) else {
+                    continue;
+                };
+                let stored_block = &blocks[stored_idx];
+                match stored_block.stored_in.get() {
+                    Some(at) if at == (host.id, input_idx) => {}
+                    None if stored_block.connected_above.get().is_none() => {
+                        stored_block.stored_in.set(Some((host.id, input_idx)))
+                    }
+                    _ => *stored = None,
+                }
+            }
+        }
+        for block in blocks.iter() {
+            let Some((host, input_idx)) = block.stored_in.get() else {
+                continue;
+            };
+            let stored_there = index_by_block_id(&host, blocks)
+                .and_then(|x| blocks[x].stored_inputs.borrow().get(input_idx).copied().flatten());
+            if stored_there != Some(block.id) {
+                block.stored_in.set(None);
+            }
+        }
+        for container in blocks.iter() {
+            for (slot, head) in container.stored_slots.borrow_mut().iter_mut().enumerate() {
+                let Some(head_idx) = head.and_then(|x| index_by_block_id(&x, blocks)) else {
+                    continue;
+                };
+                let head_block = &blocks[head_idx];
+                let free = head_block.in_slot.get().is_none_or(|x| x == (container.id, slot));
+                if free && head_block.connected_above.get().is_none() {
+                    head_block.in_slot.set(Some((container.id, slot)));
+                } else {
+                    *head = None;
+                }
+            }
+        }
+        for block in blocks.iter() {
+            let Some((container, slot)) = block.in_slot.get() else {
+                continue;
+            };
+            let heading = index_by_block_id(&container, blocks)
+                .and_then(|x| blocks[x].stored_slots.borrow().get(slot).copied().flatten());
+            if heading != Some(block.id) {
+                block.in_slot.set(None);
+            }
+        }
+
+        for block in blocks.iter() {
+            if block.connected_above.get().is_none() {
+                block.recursive_set_topmost(blocks, block.id, true);
+            }
+        }
+        for block in blocks.iter() {
+            if block.stored_in.get().is_none() {
+                block.relayout_with_inputs(blocks, font);
+                block.position_stored_inputs(blocks);
+            }
+        }
+        issues
+    }
+}
+
+impl Block {
+    /// Clears every link to a block that isn't one of the `existing` ones
+    fn forget_links_to_missing(&self, existing: &HashSet<ID>, blocks: &BlockList) {
+        let missing: HashSet<ID> = [
+            self.connected_top.get(),
+            self.connected_above.get(),
+            self.connected_below.get(),
+            self.stored_in.get().map(|x| x.0),
+            self.in_slot.get().map(|x| x.0),
+        ]
+        .into_iter()
+        .chain(self.stored_inputs.borrow().iter().copied())
+        .chain(self.stored_slots.borrow().iter().copied())
+        .flatten()
+        .filter(|x| !existing.contains(x))
+        .collect();
+        if !missing.is_empty() {
+            self.forget_links_to(&missing, blocks);
+        }
+    }
+}
+
+/// Blocks that are laid out and walked through from this one - The one below it and the ones in its inputs and slots
+fn children(block: &Block) -> Vec<ID> {
+    let mut children: Vec<ID> = block.connected_below.get().into_iter().collect();
+    children.extend(block.stored_inputs.borrow().iter().flatten());
+    children.extend(block.stored_slots.borrow().iter().flatten());
+    children
+}
+
+/// Removes the link from `parent` to `child`, whichever kind it is - The other side of inputs and slots is dropped by the rest of [`WorkSpace::repair`]
+fn cut_link(parent: &Block, child: &Block) {
+    if parent.connected_below.get() == Some(child.id) {
+        parent.connected_below.set(None);
+        if child.connected_above.get() == Some(parent.id) {
+            child.connected_above.set(None);
+        }
+    }
+    for stored in parent.stored_inputs.borrow_mut().iter_mut() {
+        if *stored == Some(child.id) {
+            *stored = None;
+        }
+    }
+    for head in parent.stored_slots.borrow_mut().iter_mut() {
+        if *head == Some(child.id) {
+            *head = None;
+        }
+    }
+}
+
+/// Loops formed by following the blocks below and the blocks stored in inputs and slots, each listed from the block it was entered at so the last one links back to the first
+fn find_cycles(blocks: &BlockList) -> Vec<Vec<ID>> {
+    let mut cycles = Vec::new();
+    let mut done: HashSet<ID> = HashSet::new();
+    for start in blocks.iter() {
+        if done.contains(&start.id) {
+            continue;
+        }
+        // Depth first without recursion, each block on the path with the children it still has to look at
+        let mut path: Vec<(ID, Vec<ID>)> = Vec::from([(start.id, children(start))]);
+        let mut on_path: HashSet<ID> = HashSet::from([start.id]);
+        while let Some((_, pending)) = path.last_mut() {
+            let Some(child) = pending.pop() else {
+                if let Some((id, _)) = path.pop() {
+                    on_path.remove(&id);
+                    done.insert(id);
+                }
+                continue;
+            };
+            if done.contains(&child) {
+                continue;
+            }
+            if on_path.contains(&child) {
+                let entered = path.iter().position(|x| x.0 == child).unwrap_or_default();
+                cycles.push(path[entered..].iter().map(|x| x.0).collect());
+                continue;
+            }
+            let Some(idx) = index_by_block_id(&child, blocks) else {
+                continue;
+            };
+            on_path.insert(child);
+            path.push((child, children(&blocks[idx])));
+        }
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::LogicFast;
+    use crate::test_support::{add_block, font, load_example};
+
+    fn has_cycle(issues: &[IntegrityIssue]) -> bool {
+        issues.iter().any(|x| matches!(x, IntegrityIssue::Cycle { .. }))
+    }
+
+    #[test]
+    fn container_holding_itself_is_cut() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let container = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let block = workspace.blocks.get_by_id(&container).unwrap();
+        block.stored_slots.borrow_mut()[0] = Some(container);
+        block.in_slot.set(Some((container, 0)));
+
+        assert!(has_cycle(&workspace.validate()));
+        assert!(has_cycle(&workspace.repair(&font)));
+        assert_eq!(workspace.validate(), []);
+        let block = workspace.blocks.get_by_id(&container).unwrap();
+        assert_eq!(block.stored_slots.borrow()[0], None);
+        assert_eq!(block.in_slot.get(), None);
+        block.layout_stack(&workspace.blocks);
+    }
+
+    #[test]
+    fn inline_blocks_stored_in_each_other_are_cut() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let first = add_block(&mut workspace, &loaded, &names, &font, "add_numbers");
+        let second = add_block(&mut workspace, &loaded, &names, &font, "add_numbers");
+        for (host, stored) in [(first, second), (second, first)] {
+            workspace.blocks.get_by_id(&host).unwrap().stored_inputs.borrow_mut()[0] = Some(stored);
+            workspace.blocks.get_by_id(&stored).unwrap().stored_in.set(Some((host, 0)));
+        }
+
+        assert!(has_cycle(&workspace.validate()));
+        workspace.repair(&font);
+        assert_eq!(workspace.validate(), []);
+        // Exactly one of them still holds the other
+        let held: usize = [first, second]
+            .iter()
+            .map(|x| workspace.blocks.get_by_id(x).unwrap().get_all_ids_of_all_inputs(&workspace.blocks).len())
+            .sum();
+        assert_eq!(held, 1);
+    }
+
+    #[test]
+    fn loaded_containment_cycles_are_repaired() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let outer = add_block(&mut workspace, &loaded, &names, &font, "if_else");
+        let inner = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        // Each container heads a slot of the other
+        for (container, head) in [(outer, inner), (inner, outer)] {
+            workspace.blocks.get_by_id(&container).unwrap().stored_slots.borrow_mut()[0] = Some(head);
+            workspace.blocks.get_by_id(&head).unwrap().in_slot.set(Some((container, 0)));
+        }
+        let saved = workspace.to_saved();
+
+        let mut restored = WorkSpace::new(&logic);
+        let report = restored.restore(&saved, &loaded.definitions(), &names, &font, &loaded.types);
+        assert!(has_cycle(&report.repaired));
+        assert_eq!(restored.validate(), []);
+    }
+}
@@ -0,0 +1,264 @@
+use fontdue::Font;
+use mirl::platform::KeyCode;
+
+use crate::all::index_by_block_id;
+use crate::internal::{Block, BlockList, ID};
+use crate::SizeType;
+
+/// What the editor wants to happen after handling the keys of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// Keep the editor open
+    Editing,
+    /// Keep the typed text and close the editor
+    Done,
+    /// Restore the text from before editing and close the editor
+    Cancelled,
+}
+
+/// Text editing state of the one input slot being typed into
+///
+/// mirl only reports which keys are down, so typed text comes from newly pressed keys and the clipboard only lives inside the app
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiteralEditor {
+    /// Block owning the edited input
+    pub block: ID,
+    pub input_idx: usize,
+    pub text: String,
+    /// Literal from before editing, put back when cancelled
+    original: Option<String>,
+    /// Position in chars, not bytes
+    caret: usize,
+    /// Other end of the selection, the caret being one end
+    anchor: Option<usize>,
+    /// Keys down last frame, only keys going down type anything
+    last_keys: Vec<KeyCode>,
+}
+
+impl LiteralEditor {
+    /// Starts editing input `input_idx` of `block` with the caret behind the current literal
+    ///
+    /// `keys_down` are ignored until released so the keys held while clicking don't type anything
+    pub fn new(block: &Block, input_idx: usize, keys_down: Vec<KeyCode>) -> Self {
+        let original = block
+            .stored_literals
+            .borrow()
+            .get(input_idx)
+            .cloned()
+            .flatten();
+        let text = original.clone().unwrap_or_default();
+        Self {
+            block: block.id,
+            input_idx,
+            caret: text.chars().count(),
+            text,
+            original,
+            anchor: None,
+            last_keys: keys_down,
+        }
+    }
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+    /// Start and end (in chars) of the selected text, None if nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor.filter(|x| *x != self.caret)?;
+        Some((anchor.min(self.caret), anchor.max(self.caret)))
+    }
+    fn length(&self) -> usize {
+        self.text.chars().count()
+    }
+    fn byte_index(&self, char_index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(char_index)
+            .map_or(self.text.len(), |x| x.0)
+    }
+    /// Text in front of the given char position
+    pub fn text_before(&self, char_index: usize) -> &str {
+        &self.text[..self.byte_index(char_index)]
+    }
+    fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.text[self.byte_index(start)..self.byte_index(end)].to_string())
+    }
+    /// Removes the selected text, returns whether there was any
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        let range = self.byte_index(start)..self.byte_index(end);
+        self.text.replace_range(range, "");
+        self.caret = start;
+        self.anchor = None;
+        true
+    }
+    /// Replaces the selection with `text`, or inserts it at the caret
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        let at = self.byte_index(self.caret);
+        self.text.insert_str(at, text);
+        self.caret += text.chars().count();
+    }
+    fn backspace(&mut self) {
+        if !self.delete_selection() && self.caret > 0 {
+            self.caret -= 1;
+            let at = self.byte_index(self.caret);
+            self.text.remove(at);
+        }
+    }
+    fn delete(&mut self) {
+        if !self.delete_selection() && self.caret < self.length() {
+            let at = self.byte_index(self.caret);
+            self.text.remove(at);
+        }
+    }
+    /// Moves the caret, keeping (or starting) a selection when `select`
+    pub fn move_caret(&mut self, to: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = to.min(self.length());
+    }
+    fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.caret = self.length();
+    }
+    /// Char position closest to `x`, measured from where the text starts
+    pub fn caret_at(&self, x: SizeType, font_size: f32, font: &Font) -> usize {
+        let mut closest = (0, x.abs());
+        for idx in 1..=self.length() {
+            let width = mirl::render::get_length_of_string(self.text_before(idx), font_size, font)
+                as SizeType;
+            if (width - x).abs() < closest.1 {
+                closest = (idx, (width - x).abs());
+            }
+        }
+        closest.0
+    }
+    /// Types, moves the caret and uses the clipboard for every key that went down since the last frame
+    pub fn handle_keys(
+        &mut self,
+        keys_down: Vec<KeyCode>,
+        shift: bool,
+        control: bool,
+        clipboard: &mut String,
+    ) -> EditOutcome {
+        let pressed: Vec<KeyCode> = keys_down
+            .iter()
+            .filter(|x| !self.last_keys.contains(x))
+            .copied()
+            .collect();
+        self.last_keys = keys_down;
+
+        for key in pressed {
+            match key {
+                KeyCode::Enter | KeyCode::KeyPadEnter | KeyCode::Tab => return EditOutcome::Done,
+                KeyCode::Escape => return EditOutcome::Cancelled,
+                KeyCode::Backspace => self.backspace(),
+                KeyCode::Delete => self.delete(),
+                KeyCode::Left => {
+                    let to = match (self.selection(), shift) {
+                        (Some((start, _)), false) => start,
+                        _ => self.caret.saturating_sub(1),
+                    };
+                    self.move_caret(to, shift);
+                }
+                KeyCode::Right => {
+                    let to = match (self.selection(), shift) {
+                        (Some((_, end)), false) => end,
+                        _ => self.caret + 1,
+                    };
+                    self.move_caret(to, shift);
+                }
+                KeyCode::Home | KeyCode::Up => self.move_caret(0, shift),
+                KeyCode::End | KeyCode::Down => self.move_caret(self.length(), shift),
+                KeyCode::A if control => self.select_all(),
+                KeyCode::C if control => {
+                    if let Some(selected) = self.selected_text() {
+                        *clipboard = selected;
+                    }
+                }
+                KeyCode::X if control => {
+                    if let Some(selected) = self.selected_text() {
+                        *clipboard = selected;
+                        self.delete_selection();
+                    }
+                }
+                KeyCode::V if control => {
+                    // Literals are single line
+                    let pasted = clipboard.replace(['\n', '\r', '\t'], " ");
+                    self.insert(&pasted);
+                }
+                // Other shortcuts shouldn't type their letter
+                _ if control => {}
+                _ => {
+                    if let Some(typed) = typed_text(key, shift) {
+                        self.insert(&typed);
+                    }
+                }
+            }
+        }
+        EditOutcome::Editing
+    }
+    /// Writes the text into the block (an empty text clears the literal) and resizes the block and its hosts
+    pub fn apply(&self, blocks: &BlockList, font: &Font) {
+        let literal = Some(self.text.clone()).filter(|x| !x.is_empty());
+        self.set_literal(literal, blocks, font);
+    }
+    /// Puts back the literal from before editing
+    pub fn revert(&self, blocks: &BlockList, font: &Font) {
+        self.set_literal(self.original.clone(), blocks, font);
+    }
+    fn set_literal(&self, literal: Option<String>, blocks: &BlockList, font: &Font) {
+        if let Some(idx) = index_by_block_id(&self.block, blocks) {
+            blocks[idx].set_literal(self.input_idx, literal, blocks, font);
+        }
+    }
+}
+
+/// Text a key types, None for keys that don't type anything
+///
+/// Shifted symbols follow the US layout as mirl only knows the key, not the character the OS would produce
+pub fn typed_text(key: KeyCode, shift: bool) -> Option<String> {
+    if shift {
+        let shifted = match key {
+            KeyCode::Num1 => Some("!"),
+            KeyCode::Num2 => Some("@"),
+            KeyCode::Num3 => Some("#"),
+            KeyCode::Num4 => Some("$"),
+            KeyCode::Num5 => Some("%"),
+            KeyCode::Num6 => Some("^"),
+            KeyCode::Num7 => Some("&"),
+            KeyCode::Num8 => Some("*"),
+            KeyCode::Num9 => Some("("),
+            KeyCode::Num0 => Some(")"),
+            KeyCode::Minus => Some("_"),
+            KeyCode::Equal => Some("+"),
+            KeyCode::LeftBracket => Some("{"),
+            KeyCode::RightBracket => Some("}"),
+            KeyCode::Backslash => Some("|"),
+            KeyCode::Semicolon => Some(":"),
+            KeyCode::Apostrophe => Some("\""),
+            KeyCode::Comma => Some("<"),
+            KeyCode::Period => Some(">"),
+            KeyCode::Slash => Some("?"),
+            KeyCode::Grave => Some("~"),
+            _ => None,
+        };
+        if let Some(shifted) = shifted {
+            return Some(shifted.to_string());
+        }
+    }
+    let text = key.to_user_friendly_string()?;
+    if text.chars().any(char::is_control) {
+        return None;
+    }
+    if shift {
+        Some(text)
+    } else {
+        Some(text.to_lowercase())
+    }
+}
@@ -1,12 +1,27 @@
 pub mod block;
 pub mod block_input;
+pub mod block_kind;
+pub mod block_list;
 pub mod camera;
+pub mod container;
+pub mod delete;
+pub mod dropdown;
+pub mod duplicate;
+pub mod history;
 pub mod id;
+pub mod integrity;
+pub mod literal_editor;
+pub mod palette;
 pub mod workspace;
+pub mod save;
+pub mod spatial_grid;
 pub mod svg;
 
 pub use block::Block;
 pub use block_input::BlockInput;
+pub use block_kind::BlockKind;
+pub use block_list::BlockList;
 pub use camera::Camera;
 pub use id::ID;
+pub use literal_editor::LiteralEditor;
 pub use workspace::WorkSpace;
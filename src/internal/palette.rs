@@ -0,0 +1,99 @@
+use crate::internal::Block;
+
+/// Width of the sidebar, blocks dropped onto it are deleted
+pub const PALETTE_WIDTH: isize = 360;
+/// Height of the row naming a group
+pub const PALETTE_HEADER_HEIGHT: isize = 20;
+/// Space around and between the rows
+pub const PALETTE_PADDING: isize = 8;
+
+/// The sidebar on the left listing every loaded block - Dragging one out places a fresh copy into the workspace, the listed blocks themselves are never edited
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    /// How far the list is scrolled down, in pixels
+    scroll: isize,
+}
+
+/// One row of the palette in screen coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteRow<'a> {
+    /// Starts the group of blocks of one plugin with the same output type
+    Header {
+        plugin: &'a str,
+        output: &'a str,
+        /// Index into the output colors, shared by every block of the group
+        color_id: usize,
+        y: isize,
+    },
+    Block { block: &'a Block, x: isize, y: isize },
+}
+
+impl PaletteRow<'_> {
+    fn bottom(&self) -> isize {
+        match self {
+            PaletteRow::Header { y, .. } => y + PALETTE_HEADER_HEIGHT,
+            PaletteRow::Block { block, y, .. } => y + block.full_height() as isize,
+        }
+    }
+}
+
+impl Palette {
+    /// Is the screen position on the sidebar
+    pub fn contains(&self, pos: (isize, isize)) -> bool {
+        (0..PALETTE_WIDTH).contains(&pos.0)
+    }
+    /// Every block below the header of its group, groups sorted by plugin then output type
+    pub fn layout<'a>(&self, definitions: impl Iterator<Item = &'a Block>) -> Vec<PaletteRow<'a>> {
+        let mut groups: Vec<((&str, &str), Vec<&Block>)> = Vec::new();
+        for block in definitions {
+            let key = (block.plugin.as_str(), block.output.as_str());
+            match groups.iter_mut().find(|x| x.0 == key) {
+                Some(group) => group.1.push(block),
+                None => groups.push((key, Vec::from([block]))),
+            }
+        }
+        groups.sort_by_key(|x| x.0);
+
+        let mut rows = Vec::new();
+        let mut y = PALETTE_PADDING - self.scroll;
+        for ((plugin, output), blocks) in groups {
+            rows.push(PaletteRow::Header {
+                plugin,
+                output,
+                color_id: blocks[0].block_color_id,
+                y,
+            });
+            y += PALETTE_HEADER_HEIGHT + PALETTE_PADDING;
+            for block in blocks {
+                rows.push(PaletteRow::Block {
+                    block,
+                    x: PALETTE_PADDING,
+                    y,
+                });
+                y += block.full_height() as isize + PALETTE_PADDING;
+            }
+        }
+        rows
+    }
+    /// Listed block at the screen position and where on it the position is
+    pub fn block_at<'a>(
+        rows: &[PaletteRow<'a>],
+        pos: (isize, isize),
+    ) -> Option<(&'a Block, (isize, isize))> {
+        rows.iter().find_map(|row| match row {
+            PaletteRow::Block { block, x, y } => {
+                let offset = (pos.0 - x, pos.1 - y);
+                let inside = (0..block.width.get() as isize).contains(&offset.0)
+                    && (0..block.full_height() as isize).contains(&offset.1);
+                inside.then_some((*block, offset))
+            }
+            PaletteRow::Header { .. } => None,
+        })
+    }
+    /// Scrolls without going past the first or last row
+    pub fn scroll_by(&mut self, amount: isize, rows: &[PaletteRow], view_height: isize) {
+        let content_height = rows.last().map_or(0, |x| x.bottom() + self.scroll) + PALETTE_PADDING;
+        let max = (content_height - view_height).max(0);
+        self.scroll = (self.scroll + amount).clamp(0, max);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashSet;
+
+use fontdue::Font;
+use mirl::platform::FileSystem;
+use serde::{Deserialize, Serialize};
+
+use crate::all::index_by_block_id;
+use crate::internal::integrity::IntegrityIssue;
+use crate::internal::{Block, Camera, WorkSpace, ID};
+use crate::logic::Physics;
+use crate::plugin::TypeHierarchy;
+use crate::CoordinateType;
+
+/// Extension of procedure files, the same one `install_double_click_logic.rs` registers
+pub const PROCEDURE_FILE_EXTENSION: &str = ".proc.json";
+/// Bump this when [`SavedWorkSpace`] changes in a way older versions can't read
+pub const PROCEDURE_FILE_VERSION: u32 = 1;
+
+/// Everything needed to rebuild a [`WorkSpace`] - Block definitions themselves are not saved, they come from the plugins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedWorkSpace {
+    pub version: u32,
+    pub block_counter: usize,
+    pub camera: Camera,
+    pub blocks: Vec<SavedBlock>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedBlock {
+    pub id: ID,
+    /// Used to find the plugin definition again when loading
+    pub internal_name: String,
+    /// Preferred over other plugins defining the same `internal_name`
+    #[serde(default)]
+    pub plugin: String,
+    pub x: CoordinateType,
+    pub y: CoordinateType,
+    #[serde(default)]
+    pub stored_inputs: Vec<Option<ID>>,
+    #[serde(default)]
+    pub stored_literals: Vec<Option<String>>,
+    #[serde(default)]
+    pub connected_top: Option<ID>,
+    #[serde(default)]
+    pub connected_above: Option<ID>,
+    #[serde(default)]
+    pub connected_below: Option<ID>,
+    /// Head of the stack in each slot of a container
+    #[serde(default)]
+    pub stored_slots: Vec<Option<ID>>,
+}
+
+impl SavedBlock {
+    pub fn from_block(block: &Block) -> Self {
+        Self {
+            id: block.id,
+            internal_name: block.internal_name.clone(),
+            plugin: block.plugin.clone(),
+            x: block.x.get(),
+            y: block.y.get(),
+            stored_inputs: block.stored_inputs.borrow().clone(),
+            stored_literals: block.stored_literals.borrow().clone(),
+            connected_top: block.connected_top.get(),
+            connected_above: block.connected_above.get(),
+            connected_below: block.connected_below.get(),
+            stored_slots: block.stored_slots.borrow().clone(),
+        }
+    }
+}
+
+/// What went wrong while loading without being bad enough to abort
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadReport {
+    /// Blocks whose `internal_name` no plugin defines anymore, they are left out of the workspace
+    pub missing_definitions: Vec<(ID, String)>,
+    /// Blocks whose definition exists but could not be rebuilt (id, internal name, reason)
+    pub failed: Vec<(ID, String, String)>,
+    /// Stored blocks whose output the input doesn't accept, they are kept as they are
+    pub type_mismatches: Vec<TypeMismatch>,
+    /// Broken links between the loaded blocks, already repaired
+    pub repaired: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeMismatch {
+    pub stored: ID,
+    pub output: String,
+    pub host: ID,
+    pub input_idx: usize,
+    pub input_type: String,
+}
+
+impl LoadReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_definitions.is_empty()
+            && self.failed.is_empty()
+            && self.type_mismatches.is_empty()
+            && self.repaired.is_empty()
+    }
+}
+
+impl std::fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for (id, internal_name) in &self.missing_definitions {
+            writeln!(
+                f,
+                "Block {} ('{}') was skipped as no loaded plugin defines it",
+                id, internal_name
+            )?;
+        }
+        for (id, internal_name, reason) in &self.failed {
+            writeln!(
+                f,
+                "Block {} ('{}') was skipped as it could not be rebuilt: {}",
+                id, internal_name, reason
+            )?;
+        }
+        for mismatch in &self.type_mismatches {
+            writeln!(
+                f,
+                "Block {} ('{}') is stored in input {} of block {} which expects '{}'",
+                mismatch.stored,
+                mismatch.output,
+                mismatch.input_idx,
+                mismatch.host,
+                mismatch.input_type
+            )?;
+        }
+        for issue in &self.repaired {
+            writeln!(f, "Repaired: {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl<L: Physics> WorkSpace<'_, L> {
+    pub fn to_saved(&self) -> SavedWorkSpace {
+        SavedWorkSpace {
+            version: PROCEDURE_FILE_VERSION,
+            block_counter: self.get_block_counter(),
+            camera: self.camera.clone(),
+            blocks: self.blocks.iter().map(SavedBlock::from_block).collect(),
+        }
+    }
+    pub fn save<F: FileSystem>(
+        &self,
+        file_system: &F,
+        path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::to_string_pretty(&self.to_saved())?;
+        file_system.write_to_file(path, json.as_bytes())?;
+        Ok(())
+    }
+    /// Replaces the blocks and camera of this workspace with the ones stored in `path`
+    pub fn load<F: FileSystem>(
+        &mut self,
+        file_system: &F,
+        path: &str,
+        definitions: &[Block],
+        output_color_names: &[String],
+        font: &Font,
+        types: &TypeHierarchy,
+    ) -> Result<LoadReport, Box<dyn std::error::Error>> {
+        let contents = file_system.get_file_contents(path)?.as_string()?;
+        let saved: SavedWorkSpace = serde_json::from_str(&contents)?;
+        if saved.version > PROCEDURE_FILE_VERSION {
+            return Err(format!(
+                "'{}' was saved with file version {} but only up to {} is supported",
+                path, saved.version, PROCEDURE_FILE_VERSION
+            )
+            .into());
+        }
+        Ok(self.restore(&saved, definitions, output_color_names, font, types))
+    }
+    /// Rebuilds every saved block from the plugin definition with the same `internal_name`, preferring the plugin it was saved with
+    ///
+    /// Links to blocks that could not be rebuilt are dropped, splitting their stacks
+    pub fn restore(
+        &mut self,
+        saved: &SavedWorkSpace,
+        definitions: &[Block],
+        output_color_names: &[String],
+        font: &Font,
+        types: &TypeHierarchy,
+    ) -> LoadReport {
+        let mut report = LoadReport::default();
+        let mut restored = Vec::new();
+        for saved_block in &saved.blocks {
+            let same_name = |x: &&Block| x.internal_name == saved_block.internal_name;
+            let Some(definition) = definitions
+                .iter()
+                .filter(same_name)
+                .find(|x| x.plugin == saved_block.plugin)
+                .or_else(|| definitions.iter().find(same_name))
+            else {
+                report
+                    .missing_definitions
+                    .push((saved_block.id, saved_block.internal_name.clone()));
+                continue;
+            };
+            let mut block = match Block::new(
+                definition.original_name.clone(),
+                definition.internal_name.clone(),
+                0,
+                0,
+                definition.kind,
+                definition.required_imports.clone(),
+                definition.required_contexts.clone(),
+                definition.file_versions.clone(),
+                definition.output.clone(),
+                definition.inputs.clone(),
+                output_color_names,
+                font,
+                self,
+                Some(saved_block.id),
+            ) {
+                Ok(block) => block,
+                Err(error) => {
+                    report.failed.push((
+                        saved_block.id,
+                        saved_block.internal_name.clone(),
+                        error.to_string(),
+                    ));
+                    continue;
+                }
+            };
+            block.plugin = definition.plugin.clone();
+            block.set_slots(definition.slots.clone());
+            block.x.set(saved_block.x);
+            block.y.set(saved_block.y);
+            restored.push((block, saved_block));
+        }
+
+        let existing: HashSet<ID> = restored.iter().map(|x| x.0.id).collect();
+        let keep = |id: Option<ID>| id.filter(|x| existing.contains(x));
+        for (block, saved_block) in &restored {
+            block.connected_top.set(keep(saved_block.connected_top));
+            block.connected_above.set(keep(saved_block.connected_above));
+            block.connected_below.set(keep(saved_block.connected_below));
+            // The definition may have gained or lost inputs since saving
+            let mut stored_inputs = block.stored_inputs.borrow_mut();
+            for (idx, stored) in stored_inputs.iter_mut().enumerate() {
+                *stored = keep(saved_block.stored_inputs.get(idx).copied().flatten());
+            }
+            let mut stored_literals = block.stored_literals.borrow_mut();
+            for (idx, stored) in stored_literals.iter_mut().enumerate() {
+                // Dropdowns always have an option picked, even in files saved before the input was one
+                *stored = saved_block
+                    .stored_literals
+                    .get(idx)
+                    .cloned()
+                    .flatten()
+                    .or_else(|| block.inputs[idx].default_literal());
+            }
+            // Slots are matched by position like inputs
+            let mut stored_slots = block.stored_slots.borrow_mut();
+            for (idx, stored) in stored_slots.iter_mut().enumerate() {
+                *stored = keep(saved_block.stored_slots.get(idx).copied().flatten());
+            }
+        }
+
+        let highest_id = existing.iter().map(|x| usize::from(*x)).max();
+        self.raise_block_counter(
+            saved.block_counter.max(highest_id.unwrap_or_default()),
+        );
+        self.camera = saved.camera.clone();
+        self.blocks = restored.into_iter().map(|x| x.0).collect();
+
+        // Same for which input each block is stored in
+        for block in self.blocks.iter() {
+            for (input_idx, stored) in block.stored_inputs.borrow().iter().enumerate() {
+                if let Some(idx) = stored.and_then(|x| index_by_block_id(&x, &self.blocks)) {
+                    let stored = &self.blocks[idx];
+                    stored.stored_in.set(Some((block.id, input_idx)));
+                    let input_type = block.inputs[input_idx].input_type();
+                    if !types.is_assignable(&stored.output, input_type) {
+                        report.type_mismatches.push(TypeMismatch {
+                            stored: stored.id,
+                            output: stored.output.clone(),
+                            host: block.id,
+                            input_idx,
+                            input_type: input_type.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        // Same for which slot each stack is in
+        for block in self.blocks.iter() {
+            for (slot, head) in block.stored_slots.borrow().iter().enumerate() {
+                if let Some(idx) = head.and_then(|x| index_by_block_id(&x, &self.blocks)) {
+                    self.blocks[idx].in_slot.set(Some((block.id, slot)));
+                }
+            }
+        }
+        // Files edited by hand or saved by a broken version may link blocks in ways the editor can't handle - Tops are never trusted, so those aren't worth reporting
+        report.repaired = self
+            .repair(font)
+            .into_iter()
+            .filter(|x| !matches!(x, IntegrityIssue::WrongTop { .. }))
+            .collect();
+        // Recalculate what each block thinks the top of its stack is instead of trusting the file
+        for block in self.blocks.iter() {
+            if block.connected_above.get().is_none() {
+                block.recursive_set_topmost(&self.blocks, block.id, true);
+            }
+        }
+        for block in self.blocks.iter() {
+            if block.stored_in.get().is_none() {
+                block.relayout_with_inputs(&self.blocks, font);
+                block.position_stored_inputs(&self.blocks);
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::LogicFast;
+    use crate::test_support::{add_block, font, load_example, stack_below};
+
+    #[test]
+    fn saved_workspace_is_restored_as_it_was() {
+        let font = font();
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, names) = load_example(&mut workspace, &font);
+        let event = add_block(&mut workspace, &loaded, &names, &font, "on_player_join");
+        let give = add_block(&mut workspace, &loaded, &names, &font, "give_item");
+        let repeat = add_block(&mut workspace, &loaded, &names, &font, "repeat");
+        let say = add_block(&mut workspace, &loaded, &names, &font, "say_message");
+        let player = add_block(&mut workspace, &loaded, &names, &font, "nearest_player");
+        let sum = add_block(&mut workspace, &loaded, &names, &font, "add_numbers");
+        let random = add_block(&mut workspace, &loaded, &names, &font, "random_number");
+
+        stack_below(&workspace, event, give);
+        stack_below(&workspace, give, repeat);
+        let blocks = &workspace.blocks;
+        blocks.get_by_id(&say).unwrap().attach_to_slot(repeat, 0, blocks);
+        blocks.get_by_id(&player).unwrap().attach_to_input(give, 0, blocks, &font);
+        blocks.get_by_id(&sum).unwrap().attach_to_input(give, 1, blocks, &font);
+        // Nested inline block holding a literal itself
+        blocks.get_by_id(&random).unwrap().attach_to_input(sum, 1, blocks, &font);
+        blocks.get_by_id(&random).unwrap().set_literal(0, Some("5".to_string()), blocks, &font);
+        blocks.get_by_id(&sum).unwrap().set_literal(0, Some("2".to_string()), blocks, &font);
+        blocks.get_by_id(&say).unwrap().set_literal(0, Some("hello".to_string()), blocks, &font);
+        blocks.get_by_id(&give).unwrap().set_literal(2, Some("item_torch".to_string()), blocks, &font);
+        blocks.get_by_id(&event).unwrap().layout_stack(blocks);
+        for block in blocks.iter().filter(|x| x.stored_in.get().is_none()) {
+            block.position_stored_inputs(blocks);
+        }
+        workspace.camera.x += 123;
+        workspace.camera.z = 2.0;
+        // Ids of deleted blocks are not handed out again
+        workspace.increment_block_id();
+        assert_eq!(workspace.validate(), []);
+
+        let mut saved = workspace.to_saved();
+        let gone = ID::from(workspace.get_block_counter());
+        let mut removed = saved.blocks[0].clone();
+        removed.id = gone;
+        removed.internal_name = "removed_block".to_string();
+        saved.blocks.push(removed);
+        let saved: SavedWorkSpace = serde_json::from_str(&serde_json::to_string(&saved).unwrap()).unwrap();
+
+        let mut restored = WorkSpace::new(&logic);
+        let report = restored.restore(&saved, &loaded.definitions(), &names, &font, &loaded.types);
+        assert_eq!(report.missing_definitions, [(gone, "removed_block".to_string())]);
+        assert!(report.failed.is_empty() && report.type_mismatches.is_empty() && report.repaired.is_empty());
+        assert_eq!(restored.validate(), []);
+        assert_eq!(restored.get_block_counter(), workspace.get_block_counter());
+        assert_eq!(restored.camera, workspace.camera);
+        assert_eq!(restored.blocks.len(), workspace.blocks.len());
+        for block in workspace.blocks.iter() {
+            let again = restored.blocks.get_by_id(&block.id).unwrap();
+            assert_eq!(SavedBlock::from_block(again), SavedBlock::from_block(block));
+            assert_eq!(again.stored_in.get(), block.stored_in.get());
+            assert_eq!(again.in_slot.get(), block.in_slot.get());
+            assert_eq!(again.width.get(), block.width.get());
+        }
+        let give = restored.blocks.get_by_id(&give).unwrap();
+        assert_eq!(give.stored_literals.borrow()[2].as_deref(), Some("item_torch"));
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::internal::{Block, ID};
+
+/// Side length of one cell in world pixels, a bit more than a short block is wide
+pub const GRID_CELL_SIZE: isize = 128;
+
+/// First and last cell a block covers, both inclusive
+type CellRange = ((isize, isize), (isize, isize));
+
+/// Which blocks are in which part of the world, so point and distance queries only look at the blocks near them
+///
+/// Blocks are moved by setting their position directly, [`SpatialGrid::update`] catches up with that - Blocks that stayed in the same cells cost nothing
+#[derive(Debug, Clone, Default)]
+pub struct SpatialGrid {
+    cells: HashMap<(isize, isize), Vec<ID>>,
+    covered: HashMap<ID, CellRange>,
+    /// Height of the tallest block ever added, for queries reaching below blocks
+    tallest: isize,
+}
+
+/// World bounds of a block including its slots, as top left and bottom right corners
+pub fn block_bounds(block: &Block) -> ((isize, isize), (isize, isize)) {
+    let x = block.x.get() as isize;
+    let y = block.y.get() as isize;
+    (
+        (x, y),
+        (
+            x + block.width.get().ceil() as isize,
+            y + block.full_height().ceil() as isize,
+        ),
+    )
+}
+
+fn cell_of(pos: (isize, isize)) -> (isize, isize) {
+    (
+        pos.0.div_euclid(GRID_CELL_SIZE),
+        pos.1.div_euclid(GRID_CELL_SIZE),
+    )
+}
+
+impl SpatialGrid {
+    pub fn insert(&mut self, block: &Block) {
+        let (min, max) = block_bounds(block);
+        let range = (cell_of(min), cell_of(max));
+        for cell_x in range.0 .0..=range.1 .0 {
+            for cell_y in range.0 .1..=range.1 .1 {
+                self.cells.entry((cell_x, cell_y)).or_default().push(block.id);
+            }
+        }
+        self.covered.insert(block.id, range);
+        self.tallest = self.tallest.max(max.1 - min.1);
+    }
+    pub fn remove(&mut self, id: &ID) {
+        let Some(range) = self.covered.remove(id) else {
+            return;
+        };
+        for cell_x in range.0 .0..=range.1 .0 {
+            for cell_y in range.0 .1..=range.1 .1 {
+                if let Some(cell) = self.cells.get_mut(&(cell_x, cell_y)) {
+                    cell.retain(|x| x != id);
+                    if cell.is_empty() {
+                        self.cells.remove(&(cell_x, cell_y));
+                    }
+                }
+            }
+        }
+    }
+    /// Moves the block to the cells it covers now, if those changed
+    pub fn update(&mut self, block: &Block) {
+        let (min, max) = block_bounds(block);
+        if self.covered.get(&block.id) == Some(&(cell_of(min), cell_of(max))) {
+            return;
+        }
+        self.remove(&block.id);
+        self.insert(block);
+    }
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.covered.clear();
+        self.tallest = 0;
+    }
+    pub fn tallest(&self) -> isize {
+        self.tallest
+    }
+    /// Every block whose cells overlap the area between the corners (world coordinates) - May contain blocks that don't overlap the area itself, and duplicates
+    pub fn query(&self, min: (isize, isize), max: (isize, isize)) -> Vec<ID> {
+        let (first, last) = (cell_of(min), cell_of(max));
+        let area = (last.0 - first.0 + 1).saturating_mul(last.1 - first.1 + 1);
+        let mut found = Vec::new();
+        // Huge areas are cheaper to answer from the cells that actually have something in them
+        if area > self.cells.len() as isize {
+            for ((cell_x, cell_y), ids) in self.cells.iter() {
+                if (first.0..=last.0).contains(cell_x) && (first.1..=last.1).contains(cell_y) {
+                    found.extend(ids);
+                }
+            }
+            return found;
+        }
+        for cell_x in first.0..=last.0 {
+            for cell_y in first.1..=last.1 {
+                if let Some(ids) = self.cells.get(&(cell_x, cell_y)) {
+                    found.extend(ids);
+                }
+            }
+        }
+        found
+    }
+}
@@ -1,68 +1,246 @@
+use std::collections::HashMap;
 
-pub fn split_line(line:&str)->Vec<String>{
-    let splitter_char = ",".chars().next().unwrap();
-    let quotes_char = "\"".chars().next().unwrap();
-    let escape_char = "\\".chars().next().unwrap();
-    let skip_char = "\r\t";
-    let mut temp = String::new();
-    let mut total=Vec::new();
-    let mut in_quotes = false;
-    let mut escaped = false;
-    for i in line.chars(){
-        if i == quotes_char{
-            if !escaped{
-            in_quotes = !in_quotes;
-            continue;}else{
-                escaped =false;
+/// Where and why a csv file could not be read - Lines and columns start at 1
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvError {
+    pub line: usize,
+    pub column: usize,
+    pub message: &'static str,
+}
+impl std::fmt::Display for CsvError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+impl std::error::Error for CsvError {}
+
+/// One record, `line` is where it starts as quoted cells may span several lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRow {
+    pub line: usize,
+    pub cells: Vec<String>,
+}
+
+/// Reads csv as described in RFC 4180
+///
+/// Quotes inside quoted cells are doubled (`""`), quoted cells may contain commas and line breaks.
+/// A leading BOM is skipped, lines may end in `\n`, `\r\n` or a lone `\r` and blank lines are left out.
+/// Line breaks inside quoted cells are kept as `\n`.
+/// Whitespace around unquoted cells and around the quotes of quoted cells is trimmed.
+pub fn parse_csv(csv_data: &str) -> Result<Vec<CsvRow>, CsvError> {
+    let csv_data = csv_data.strip_prefix('\u{feff}').unwrap_or(csv_data);
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut row_line = 1;
+    let mut line = 1;
+    let mut column = 0;
+    // Where the currently open quote is, for the error if it never closes
+    let mut quote_start: Option<(usize, usize)> = None;
+    // The cell was quoted and its closing quote was seen
+    let mut closed_quote = false;
+
+    let mut chars = csv_data.chars().peekable();
+    while let Some(i) = chars.next() {
+        column += 1;
+        if quote_start.is_some() {
+            match i {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    column += 1;
+                    cell.push('"');
+                }
+                '"' => {
+                    quote_start = None;
+                    closed_quote = true;
+                }
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\r' | '\n' => {
+                    cell.push('\n');
+                    line += 1;
+                    column = 0;
+                }
+                _ => cell.push(i),
             }
-        }
-        if i == escape_char{
-            if !escaped{escaped = true;continue;}
-            else
-                {escaped =false;}
-        }
-        if i == splitter_char && !in_quotes{
-            total.push(temp.trim().to_string());
-            temp = "".to_string();
             continue;
         }
-        if skip_char.contains(i) && !in_quotes{
-            continue;
+        match i {
+            ',' => {
+                cells.push(finish_cell(&mut cell, closed_quote));
+                closed_quote = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => {
+                cells.push(finish_cell(&mut cell, closed_quote));
+                closed_quote = false;
+                push_row(&mut rows, &mut cells, row_line);
+                line += 1;
+                column = 0;
+                row_line = line;
+            }
+            '"' if !closed_quote && cell.trim().is_empty() => {
+                cell.clear();
+                quote_start = Some((line, column));
+            }
+            '"' => {
+                return Err(CsvError {
+                    line,
+                    column,
+                    message: "Quotes inside a cell have to be in a quoted cell and doubled (\"\")",
+                });
+            }
+            ' ' | '\t' if closed_quote => {}
+            _ if closed_quote => {
+                return Err(CsvError {
+                    line,
+                    column,
+                    message: "Expected a comma or the end of the line after the closing quote",
+                });
+            }
+            _ => cell.push(i),
         }
-
-        temp.push(i);
     }
-    total.push(temp.trim().to_string());
+    if let Some((line, column)) = quote_start {
+        return Err(CsvError {
+            line,
+            column,
+            message: "This quote is never closed",
+        });
+    }
+    cells.push(finish_cell(&mut cell, closed_quote));
+    push_row(&mut rows, &mut cells, row_line);
+    Ok(rows)
+}
 
+fn finish_cell(cell: &mut String, quoted: bool) -> String {
+    let finished = if quoted {
+        cell.clone()
+    } else {
+        cell.trim().to_string()
+    };
+    cell.clear();
+    finished
+}
 
-    total
+/// Blank lines are not rows
+fn push_row(rows: &mut Vec<CsvRow>, cells: &mut Vec<String>, line: usize) {
+    let cells = std::mem::take(cells);
+    if cells.len() == 1 && cells[0].is_empty() {
+        return;
+    }
+    rows.push(CsvRow { line, cells });
 }
 
+/// Every language column of a translation csv -> language -> key -> translated
+///
+/// Empty or missing cells are left out so they fall back to another language per key
 pub fn parse_translations(
     csv_data: &str,
-    desired_language: &str,
-    fallback_language: &str
-) -> Result<(String, std::collections::HashMap<String, String>), &'static str> {
-    let split:Vec<&str> = csv_data.split("\n").collect();
-    let language_header = split_line(split[0]);
-    let language_idx;
-    let used_language;
-    if let Some(language_idx_first) = language_header.iter().position(|x| x == desired_language){
-        language_idx = language_idx_first;
-        used_language = desired_language;
-    }else if let Some(language_idx_fallback) = language_header.iter().position(|x|x == fallback_language){
-            language_idx = language_idx_fallback;
-            used_language = fallback_language;
-        }else{
-            return Err("Unable to find desired nor fallback languages");
+) -> Result<HashMap<String, HashMap<String, String>>, CsvError> {
+    let rows = parse_csv(csv_data)?;
+    let Some(language_header) = rows.first() else {
+        return Err(CsvError {
+            line: 1,
+            column: 1,
+            message: "The file is empty, it needs at least a header",
+        });
+    };
+    if language_header.cells.len() < 2 {
+        return Err(CsvError {
+            line: language_header.line,
+            column: 1,
+            message: "The header needs a key column and at least one language",
+        });
+    }
+
+    let mut stuff = HashMap::new();
+    for language in &language_header.cells[1..] {
+        stuff.insert(language.clone(), HashMap::new());
+    }
+    for row in &rows[1..] {
+        let key = &row.cells[0];
+        if key.is_empty() {
+            continue;
         }
-    
-    let mut stuff = std::collections::HashMap::new();
-    for i in &split[1..]{
-        let extracted = split_line(i);
-        stuff.insert(extracted[0].clone(), extracted[language_idx].clone());
+        for (language_idx, language) in
+            language_header.cells.iter().enumerate().skip(1)
+        {
+            let Some(translated) = row.cells.get(language_idx) else {
+                continue;
+            };
+            if translated.is_empty() {
+                continue;
+            }
+            if let Some(table) = stuff.get_mut(language) {
+                table.insert(key.clone(), translated.clone());
+            }
+        }
+    }
+
+    Ok(stuff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(csv_data: &str) -> Vec<Vec<String>> {
+        parse_csv(csv_data).unwrap().into_iter().map(|x| x.cells).collect()
+    }
+
+    #[test]
+    fn skips_the_bom() {
+        assert_eq!(cells("\u{feff}key,en\nhello,Hello"), [["key", "en"], ["hello", "Hello"]]);
     }
 
-    Ok((used_language.to_string(), stuff))
+    #[test]
+    fn reads_crlf_and_lone_cr_line_ends() {
+        let rows = parse_csv("a,b\r\nc,d\r\ne,f\rg,h\r").unwrap();
+        assert_eq!(rows.iter().map(|x| x.line).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(rows[3].cells, ["g", "h"]);
+        // A trailing CR after a quoted cell ends the line, it isn't junk after the quote
+        assert_eq!(cells("a,\"b\"\r"), [["a", "b"]]);
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn unescapes_doubled_quotes() {
+        assert_eq!(cells("\"say \"\"hi\"\"\",x"), [["say \"hi\"", "x"]]);
+    }
+
+    #[test]
+    fn keeps_commas_and_line_breaks_in_quoted_cells() {
+        let rows = parse_csv("\"a,b\nc\",d\n\"e\r\nf\",g\nh,i").unwrap();
+        assert_eq!(rows[0].cells, ["a,b\nc", "d"]);
+        assert_eq!(rows[1].cells, ["e\nf", "g"]);
+        // Rows remember the line they start on
+        assert_eq!(rows[1].line, 3);
+        assert_eq!(rows[2].line, 5);
+    }
+
+    #[test]
+    fn leaves_out_blank_lines() {
+        let rows = parse_csv("a\n\n  \nb\n").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].line, 4);
+    }
+
+    #[test]
+    fn trims_around_cells_and_quotes() {
+        assert_eq!(cells(" a , \"b\" ,\" c \""), [["a", "b", " c "]]);
+    }
+
+    #[test]
+    fn reports_where_a_quote_is_never_closed() {
+        let error = parse_csv("a,b\nc,\"open").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.message, "This quote is never closed");
+    }
+
+    #[test]
+    fn reports_where_a_stray_quote_is() {
+        let error = parse_csv("a,b\nc,d\"e").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 4));
+        let error = parse_csv("\"a\"b").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 4));
+    }
+}
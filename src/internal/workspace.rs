@@ -1,12 +1,14 @@
 use crate::logic::Physics;
 
-use super::{Block, Camera};
+use super::history::History;
+use super::{BlockList, Camera};
 
 pub struct WorkSpace<'a, L: Physics + Sized> {
     pub logic: &'a L,
     block_counter: usize,
-    pub blocks: Vec<Block>,
+    pub blocks: BlockList,
     pub camera: Camera,
+    pub history: History,
 }
 
 impl<'a, L: Physics> WorkSpace<'a, L> {
@@ -14,12 +16,20 @@ impl<'a, L: Physics> WorkSpace<'a, L> {
         Self {
             logic,
             block_counter: 0,
-            blocks: Vec::new(),
+            blocks: BlockList::new(),
             camera: Camera::new(),
+            history: History::default(),
         }
     }
     pub fn increment_block_id(&mut self) -> usize {
         self.block_counter += 1;
         self.block_counter
     }
+    pub fn get_block_counter(&self) -> usize {
+        self.block_counter
+    }
+    /// Only ever moves the counter forward so freshly created blocks can't reuse a loaded id
+    pub fn raise_block_counter(&mut self, at_least: usize) {
+        self.block_counter = self.block_counter.max(at_least);
+    }
 }
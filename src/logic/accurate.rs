@@ -1,6 +1,6 @@
 use crate::{
-    internal::{Block, Camera, ID},
-    logic::{get_closest_input, Physics},
+    internal::{Block, BlockList, ID},
+    logic::{blocks_near, get_closest_input, Physics},
     SizeType,
 };
 
@@ -23,61 +23,30 @@ impl Physics for LogicAccurate {
         }
         false
     }
-    fn is_rectangle_visible_on_screen<F: mirl::math::Number>(
-        &self,
-        x: F,
-        y: F,
-        width: F,
-        height: F,
-        camera: &Camera,
-        buffer_width: &isize,
-        buffer_height: &isize,
-    ) -> bool {
-        let x = x.to_f64().unwrap();
-        let y = y.to_f64().unwrap();
-        let cam_x = camera.x as f64;
-        let cam_y = camera.y as f64;
-        let cam_width = *buffer_width as f64;
-        let cam_height = *buffer_height as f64;
-        let x2 = x + (width).to_f64().unwrap();
-        let y2 = y + (height).to_f64().unwrap();
-        if self.is_point_in_rectangle(x, y, cam_x, cam_y, cam_width, cam_height)
-        {
-            return true;
-        }
-        if self
-            .is_point_in_rectangle(x2, y, cam_x, cam_y, cam_width, cam_height)
-        {
-            return true;
-        }
-        if self
-            .is_point_in_rectangle(x, y2, cam_x, cam_y, cam_width, cam_height)
-        {
-            return true;
-        }
-        if self
-            .is_point_in_rectangle(x2, y2, cam_x, cam_y, cam_width, cam_height)
-        {
-            return true;
-        }
-        false
-    }
     fn get_block_in_distance(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
         pos_x: SizeType,
         pos_y: SizeType,
         max_distance: SizeType,
         blacklisted: Option<usize>,
         top: bool,
     ) -> Option<usize> {
+        // Events can't go below anything
+        if blacklisted.is_some_and(|x| !blocks[x].kind.can_connect_above()) {
+            return None;
+        }
         let mut closest = None;
         let mut min_distance = max_distance; // Start with max distance as the limit
 
-        for (block_id, block) in blocks.iter().enumerate() {
+        for block_id in blocks_near(blocks, (pos_x, pos_y), max_distance) {
+            let block = &blocks[block_id];
             if blacklisted.is_some() && block_id == blacklisted.unwrap() {
                 continue;
             }
+            if !block.kind.is_stackable() {
+                continue;
+            }
             let check_x;
             let check_y;
             if top {
@@ -109,16 +78,18 @@ impl Physics for LogicAccurate {
     }
     fn get_block_input_in_distance(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
         pos_x: SizeType,
         pos_y: SizeType,
         max_distance: SizeType,
         blacklisted: &[ID],
         _top: bool,
+        accepts: &dyn Fn(&Block, usize) -> bool,
     ) -> Option<Vec<(ID, usize)>> {
         let mut smallest = SizeType::MAX;
         let mut smallest_path = Vec::new();
-        for block in blocks {
+        for idx in blocks_near(blocks, (pos_x, pos_y), max_distance) {
+            let block = &blocks[idx];
             if blacklisted.contains(&block.id) {
                 continue;
             }
@@ -129,6 +100,7 @@ impl Physics for LogicAccurate {
                 self,
                 blacklisted,
                 blocks,
+                accepts,
             );
             if let Some(distance) = distances {
                 let (path, dis) = get_closest_input(distance);
@@ -8,6 +8,9 @@ pub trait Physics {
         y: isize,
         holes: &[(isize, isize, isize, isize)],
     ) -> bool;
+    /// Whether any part of the rectangle (world coordinates) is on the screen, touching its edge isn't enough
+    ///
+    /// The same for every physics, they only differ in how they measure distances
     fn is_rectangle_visible_on_screen<T: mirl::math::Number>(
         &self,
         x: T,
@@ -17,7 +20,17 @@ pub trait Physics {
         camera: &Camera,
         buffer_width: &isize,
         buffer_height: &isize,
-    ) -> bool;
+    ) -> bool {
+        let x = x.to_f64().unwrap() - camera.x as f64;
+        let y = y.to_f64().unwrap() - camera.y as f64;
+        // The world area on screen shrinks when zooming in
+        let cam_width = *buffer_width as f64 / camera.zoom();
+        let cam_height = *buffer_height as f64 / camera.zoom();
+        x < cam_width
+            && x + width.to_f64().unwrap() > 0.0
+            && y < cam_height
+            && y + height.to_f64().unwrap() > 0.0
+    }
     fn is_point_in_rectangle<
         T: Copy + PartialOrd + std::ops::Add<Output = T>,
     >(
@@ -43,9 +56,12 @@ pub trait Physics {
         }
         true
     }
+    /// Stackable block whose bottom is within `max_distance`, for connecting the block at `blacklisted` below it
+    ///
+    /// Always None when `blacklisted` is an event, nothing may be connected above those
     fn get_block_in_distance(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
         pos_x: SizeType,
         pos_y: SizeType,
         max_distance: SizeType,
@@ -66,24 +82,62 @@ pub trait Physics {
         width: &isize,
         height: &isize,
     ) -> bool {
+        // Including the slots of containers and the hat of events
+        let hat = if block.kind.has_hat() { HAT_HEIGHT as f64 } else { 0.0 };
         self.is_rectangle_visible_on_screen(
-            block.x.get() as SizeType,
-            block.y.get() as SizeType,
-            block.width.get(),
-            block.height.get(),
+            block.x.get() as f64,
+            block.y.get() as f64 - hat,
+            block.width.get() as f64,
+            block.full_height() as f64 + hat,
             camera,
             width,
             height,
         )
     }
+    /// Closest container slot whose start is within `max_distance` of `pos` (world coordinates), skipping `blacklisted` containers
+    ///
+    /// Measured from the container instead of as floats, world coordinates are too large for those to be exact
+    fn get_slot_in_distance(
+        &self,
+        blocks: &BlockList,
+        pos: (isize, isize),
+        max_distance: SizeType,
+        blacklisted: &[ID],
+    ) -> Option<((ID, usize), SizeType)> {
+        let mut closest = None;
+        let mut min_distance = max_distance;
+        for idx in blocks.indexes_near(pos, max_distance.ceil() as isize) {
+            let block = &blocks[idx];
+            if !block.is_container() || blacklisted.contains(&block.id) {
+                continue;
+            }
+            for slot in 0..block.slots.len() {
+                let slot_x = block.x.get() as isize + CONTAINER_ARM_WIDTH as isize;
+                let slot_y = block.y.get() as isize + block.slot_offset_y(slot) as isize;
+                let distance = self.get_distance_between_positions(
+                    (pos.0 - slot_x) as SizeType,
+                    (pos.1 - slot_y) as SizeType,
+                    0.0,
+                    0.0,
+                );
+                if distance < min_distance {
+                    min_distance = distance;
+                    closest = Some(((block.id, slot), distance));
+                }
+            }
+        }
+        closest
+    }
+    /// Path to the closest empty input, `accepts` gets the block owning the input and the input index
     fn get_block_input_in_distance(
         &self,
-        blocks: &Vec<Block>,
+        blocks: &BlockList,
         pos_x: SizeType,
         pos_y: SizeType,
         max_distance: SizeType,
         blacklisted: &[ID],
         top: bool,
+        accepts: &dyn Fn(&Block, usize) -> bool,
     ) -> Option<Vec<(ID, usize)>>;
 }
 
@@ -94,7 +148,10 @@ mod accurate;
 pub use accurate::LogicAccurate;
 
 use crate::{
-    internal::{block::InputRememberer, Block, Camera, ID},
+    internal::{
+        block::InputRememberer, block_kind::HAT_HEIGHT, container::CONTAINER_ARM_WIDTH,
+        spatial_grid::GRID_CELL_SIZE, Block, BlockList, Camera, ID,
+    },
     SizeType,
 };
 
@@ -104,6 +161,20 @@ const _: fn() = || {
     assert_impl::<LogicAccurate>();
 };
 
+/// Indexes of the blocks that may be within `reach` of the point, in drawing order
+///
+/// Floats are only accurate to about a grid cell at world coordinates, so the area is one cell larger
+pub fn blocks_near(
+    blocks: &BlockList,
+    pos: (SizeType, SizeType),
+    reach: SizeType,
+) -> Vec<usize> {
+    blocks.indexes_near(
+        (pos.0 as isize, pos.1 as isize),
+        reach.ceil() as isize + GRID_CELL_SIZE,
+    )
+}
+
 pub fn get_closest_input(
     inputs: InputRememberer,
 ) -> (Vec<(ID, usize)>, SizeType) {
@@ -112,8 +183,8 @@ pub fn get_closest_input(
 
     for (spot, more, distance) in inputs.internal_inputs {
         let mut current_path = vec![(inputs.own_id, spot)];
-        let distance = distance.unwrap();
 
+        // Occupied inputs have no distance of their own, only the inputs of the block inside them count
         if let Some(deeper) = more {
             let (deeper_path, deeper_distance) = get_closest_input(deeper);
             if deeper_distance < smallest {
@@ -121,7 +192,7 @@ pub fn get_closest_input(
                 smallest_path = current_path;
                 smallest = deeper_distance;
             }
-        } else if distance < smallest {
+        } else if let Some(distance) = distance.filter(|x| *x < smallest) {
             smallest = distance;
             smallest_path = current_path;
         }
@@ -129,3 +200,60 @@ pub fn get_closest_input(
 
     (smallest_path, smallest)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (isize, isize) = (1000, 600);
+
+    /// Visibility of a rectangle given relative to the camera, whose world position is as large as in the editor
+    fn visible<L: Physics>(logic: &L, zoom: f64, x: f64, y: f64, width: f64, height: f64) -> bool {
+        let camera = Camera { z: zoom, ..Camera::new() };
+        logic.is_rectangle_visible_on_screen(
+            camera.x as f64 + x,
+            camera.y as f64 + y,
+            width,
+            height,
+            &camera,
+            &SCREEN.0,
+            &SCREEN.1,
+        )
+    }
+
+    fn check_visibility<L: Physics>(logic: &L) {
+        // Fully inside
+        assert!(visible(logic, 1.0, 10.0, 10.0, 50.0, 50.0));
+        // Touching an edge from outside isn't overlapping
+        assert!(!visible(logic, 1.0, -50.0, 10.0, 50.0, 50.0));
+        assert!(!visible(logic, 1.0, 1000.0, 10.0, 50.0, 50.0));
+        assert!(!visible(logic, 1.0, 10.0, -50.0, 50.0, 50.0));
+        assert!(!visible(logic, 1.0, 10.0, 600.0, 50.0, 50.0));
+        // Straddling each edge
+        assert!(visible(logic, 1.0, -25.0, 10.0, 50.0, 50.0));
+        assert!(visible(logic, 1.0, 975.0, 10.0, 50.0, 50.0));
+        assert!(visible(logic, 1.0, 10.0, -25.0, 50.0, 50.0));
+        assert!(visible(logic, 1.0, 10.0, 575.0, 50.0, 50.0));
+        // Larger than the screen, no corner on it
+        assert!(visible(logic, 1.0, -100.0, -100.0, 3000.0, 3000.0));
+        assert!(visible(logic, 1.0, -100.0, 100.0, 3000.0, 10.0));
+        // Fully outside
+        assert!(!visible(logic, 1.0, 2000.0, 2000.0, 50.0, 50.0));
+        assert!(!visible(logic, 1.0, -2000.0, 10.0, 50.0, 50.0));
+        // Zoomed in only half the world width is on screen, zoomed out twice of it
+        assert!(!visible(logic, 2.0, 600.0, 10.0, 50.0, 50.0));
+        assert!(visible(logic, 2.0, 475.0, 10.0, 50.0, 50.0));
+        assert!(visible(logic, 0.5, 1500.0, 10.0, 50.0, 50.0));
+        assert!(!visible(logic, 0.5, 2000.0, 10.0, 50.0, 50.0));
+    }
+
+    #[test]
+    fn fast_visibility() {
+        check_visibility(&LogicFast::new());
+    }
+
+    #[test]
+    fn accurate_visibility() {
+        check_visibility(&LogicAccurate::new());
+    }
+}
@@ -10,12 +10,20 @@ mod internal; // Workspace/Blocks/Camera
 mod logic; // 'Physics'
 use all::main_loop;
 mod idk;
+mod generate; // Blocks -> json code
+mod settings; // Command line/Plugin paths
+mod plugin; // Plugin loading
+mod bench; // Frame cost without a window
+#[cfg(test)]
+mod test_support; // Fixtures shared by the tests
 use mirl::platform::{FileSystem, WindowSettings};
 use mirl::unsigned_to_signed;
 
 use mirl::platform::framework_traits::Window;
 
 const DEBUG: bool = false;
+/// Checks every link between the blocks each frame and repairs what is broken, only in debug builds as it walks every block
+const REPAIR_EVERY_FRAME: bool = false;
 type CoordinateType = u32;
 type CoordinateTypeSigned = unsigned_to_signed!(u32);
 type SizeType = f32;
@@ -40,6 +48,29 @@ fn main() {
     }
 }
 fn actual_main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let settings = match settings::Settings::from_args(&args) {
+        Ok(settings) => settings,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    let headless = match settings.command.as_deref() {
+        Some("generate") => Some(generate::generate_headless(&settings)),
+        Some("validate-plugin") => {
+            Some(plugin::validate::validate_headless(&settings))
+        }
+        Some("bench") => Some(bench::bench_headless(&settings)),
+        _ => None,
+    };
+    if let Some(result) = headless {
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
     let buffer = mirl::platform::Buffer::new_empty(1000, 600);
     let mut framework = mirl::platform::minifb::Framework::new(
         "Rust Window",
@@ -50,7 +81,10 @@ fn actual_main() {
             "inter.ttf",
         ]))
         .unwrap();
-    main_loop(&mut framework, &file_system, &buffer).unwrap();
+    if let Err(error) = main_loop(&mut framework, &file_system, &buffer, &settings) {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
 }
 
 // #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,135 @@
+use crate::internal::block::BlockError;
+use crate::internal::BlockKind;
+
+/// Something wrong with a plugin, where it is and which block it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct PluginLoadError {
+    /// Folder name of the plugin
+    pub plugin: String,
+    /// Internal name of the block, if it could be read
+    pub block: Option<String>,
+    /// Where in `settings.json` the problem is, like `blocks[2].inputs[0].type`
+    pub json_path: Option<String>,
+    pub kind: PluginLoadErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginLoadErrorKind {
+    /// The file could not be read (path, reason)
+    MissingFile(String, String),
+    /// `settings.json` isn't valid json
+    BadJson(String),
+    /// `translation.csv` could not be parsed
+    BadTranslations(String),
+    MissingField(&'static str),
+    WrongFieldType { expected: &'static str },
+    /// `type` is something this version doesn't know
+    UnsupportedBlockType(String),
+    /// The block is loaded using its internal name instead
+    UnknownTranslationKey(String),
+    /// Another plugin loaded earlier translates this key differently, blocks of this plugin still use their own
+    TranslationConflict { key: String, kept_plugin: String },
+    /// The translated name has a different amount of `{}` than there are inputs
+    InputCountMismatch { placeholders: usize, inputs: usize },
+    /// `settings.json` doesn't match `schema/plugin_settings.schema.json`
+    SchemaViolation(String),
+    /// Another block already uses this name (where the first one is)
+    DuplicateBlockName(String),
+    /// A plugin with the same folder name was already loaded from another search root (its path), this one is skipped
+    DuplicatePluginName(String),
+    /// No input of any block in the plugin accepts this inline block's output, not even through `types`
+    UnacceptedOutputType(String),
+}
+
+impl PluginLoadError {
+    /// Errors that still let the block load
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self.kind,
+            PluginLoadErrorKind::UnknownTranslationKey(_)
+                | PluginLoadErrorKind::TranslationConflict { .. }
+                | PluginLoadErrorKind::UnsupportedBlockType(_)
+                | PluginLoadErrorKind::UnacceptedOutputType(_)
+        )
+    }
+}
+
+impl From<BlockError> for PluginLoadErrorKind {
+    fn from(value: BlockError) -> Self {
+        match value {
+            BlockError::InputCountMismatch { placeholders, inputs } => {
+                Self::InputCountMismatch { placeholders, inputs }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for PluginLoadErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::MissingFile(path, reason) => {
+                write!(f, "unable to read '{}': {}", path, reason)
+            }
+            Self::BadJson(reason) => write!(f, "invalid json: {}", reason),
+            Self::BadTranslations(reason) => {
+                write!(f, "invalid translations: {}", reason)
+            }
+            Self::MissingField(field) => write!(f, "missing field '{}'", field),
+            Self::WrongFieldType { expected } => {
+                write!(f, "expected {}", expected)
+            }
+            Self::UnsupportedBlockType(block_type) => {
+                let supported: Vec<&str> =
+                    BlockKind::ALL.iter().map(|x| x.name()).collect();
+                write!(
+                    f,
+                    "block type '{}' is not supported (expected one of {}), skipping",
+                    block_type,
+                    supported.join(", ")
+                )
+            }
+            Self::UnknownTranslationKey(key) => {
+                write!(f, "translation key '{}' not found", key)
+            }
+            Self::TranslationConflict { key, kept_plugin } => write!(
+                f,
+                "translation key '{}' is already translated differently by '{}'",
+                key, kept_plugin
+            ),
+            Self::InputCountMismatch { placeholders, inputs } => write!(
+                f,
+                "translated name has {} {{}} but the block has {} inputs",
+                placeholders, inputs
+            ),
+            Self::SchemaViolation(reason) => write!(f, "{}", reason),
+            Self::DuplicateBlockName(first) => {
+                write!(f, "block name is already used by {}", first)
+            }
+            Self::DuplicatePluginName(first) => write!(
+                f,
+                "a plugin with this name was already loaded from '{}', skipping",
+                first
+            ),
+            Self::UnacceptedOutputType(output) => write!(
+                f,
+                "no input accepts the output type '{}', the block can't be placed anywhere",
+                output
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for PluginLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "[{}]", self.plugin)?;
+        if let Some(json_path) = &self.json_path {
+            write!(f, " {}", json_path)?;
+        }
+        if let Some(block) = &self.block {
+            write!(f, " ({})", block)?;
+        }
+        write!(f, ": {}", self.kind)
+    }
+}
+
+impl std::error::Error for PluginLoadError {}
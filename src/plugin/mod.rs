@@ -0,0 +1,575 @@
+pub mod error;
+pub mod translation;
+pub mod types;
+pub mod validate;
+pub use error::{PluginLoadError, PluginLoadErrorKind};
+pub use translation::{Translations, FALLBACK_LANGUAGE};
+pub use types::TypeHierarchy;
+
+use fontdue::Font;
+use mirl::platform::FileSystem;
+use serde_json::Value;
+
+use crate::all::generate_random_color;
+use crate::internal::container::StatementSlot;
+use crate::internal::{Block, BlockInput, BlockKind, BlockList, WorkSpace};
+use crate::logic::Physics;
+
+/// Everything [`load_blocks`] could make sense of, plus everything it couldn't
+#[derive(Debug, Default)]
+pub struct LoadedPlugins {
+    pub action_blocks: Vec<Block>,
+    pub inline_blocks: Vec<Block>,
+    pub translations: Translations,
+    pub types: TypeHierarchy,
+    pub errors: Vec<PluginLoadError>,
+}
+
+impl LoadedPlugins {
+    /// All loaded blocks, used to re-resolve saved blocks
+    pub fn definitions(&self) -> Vec<Block> {
+        self.iter_definitions().cloned().collect()
+    }
+    /// All loaded blocks without copying them, the palette lists them every frame
+    pub fn iter_definitions(&self) -> impl Iterator<Item = &Block> {
+        self.action_blocks.iter().chain(self.inline_blocks.iter())
+    }
+    /// One line per diagnostic
+    pub fn error_messages(&self) -> Vec<String> {
+        self.errors.iter().map(|x| x.to_string()).collect()
+    }
+    /// Renames the palette blocks into `language` - Blocks already placed are renamed with [`translate_blocks`]
+    pub fn set_language(&mut self, language: &str, font: &Font) -> Vec<PluginLoadError> {
+        self.translations.set_language(language);
+        let errors = rename_blocks(
+            &self.translations,
+            self.action_blocks.iter_mut().chain(self.inline_blocks.iter_mut()),
+        );
+        // Nothing is stored in the inputs of palette blocks
+        for block in self.iter_definitions() {
+            block.relayout_with_inputs(&BlockList::new(), font);
+        }
+        errors
+    }
+}
+
+/// Re-splits the name of every block, relabels dropdown options and slots and lays them out again - Blocks whose new name doesn't fit keep the old one
+pub fn translate_blocks(
+    translations: &Translations,
+    blocks: &mut BlockList,
+    font: &Font,
+) -> Vec<PluginLoadError> {
+    let errors = rename_blocks(translations, blocks.iter_mut());
+    // Stored blocks change size too, so only lay out once every name is known
+    for block in blocks.iter() {
+        if block.stored_in.get().is_none() {
+            block.relayout_with_inputs(blocks, font);
+            block.position_stored_inputs(blocks);
+        }
+    }
+    errors
+}
+
+/// The translating part of [`translate_blocks`], without laying anything out
+fn rename_blocks<'a>(
+    translations: &Translations,
+    blocks: impl IntoIterator<Item = &'a mut Block>,
+) -> Vec<PluginLoadError> {
+    let mut errors = Vec::new();
+    for block in blocks {
+        let plugin = block.plugin.clone();
+        for input in block.inputs.iter_mut() {
+            input.translate_labels(|key| translations.get(&plugin, key).cloned());
+        }
+        translate_slots(translations, block);
+        let name = translations
+            .get(&block.plugin, &block.internal_name)
+            .cloned()
+            .unwrap_or_else(|| untranslated_name(&block.internal_name, block.inputs.len()));
+        if name == block.original_name {
+            continue;
+        }
+        if let Err(error) = block.rename(name) {
+            errors.push(PluginLoadError {
+                plugin: block.plugin.clone(),
+                block: Some(block.internal_name.clone()),
+                json_path: None,
+                kind: error.into(),
+            });
+        }
+    }
+    errors
+}
+
+/// Still usable, just not pretty
+fn untranslated_name(internal_name: &str, input_count: usize) -> String {
+    format!("{}{}", internal_name, " {}".repeat(input_count))
+}
+
+/// Which plugin/block is being loaded, so errors know where they came from
+struct Context<'a> {
+    plugin: String,
+    block: Option<String>,
+    errors: &'a mut Vec<PluginLoadError>,
+}
+
+impl Context<'_> {
+    fn push(&mut self, json_path: Option<String>, kind: PluginLoadErrorKind) {
+        self.errors.push(PluginLoadError {
+            plugin: self.plugin.clone(),
+            block: self.block.clone(),
+            json_path,
+            kind,
+        });
+    }
+    fn missing(&mut self, json_path: String, field: &'static str) {
+        self.push(Some(json_path), PluginLoadErrorKind::MissingField(field));
+    }
+    fn wrong_type(&mut self, json_path: String, expected: &'static str) {
+        self.push(
+            Some(json_path),
+            PluginLoadErrorKind::WrongFieldType { expected },
+        );
+    }
+}
+
+/// Loads every plugin folder found in `plugin_paths`
+///
+/// Broken blocks are skipped and reported in [`LoadedPlugins::errors`], every valid block is still loaded
+pub fn load_blocks<F: FileSystem, L: Physics>(
+    file_system: &F,
+    block_output_types: &mut Vec<String>,
+    block_output_colors: &mut Vec<u32>,
+    font: &Font,
+    workspace: &mut WorkSpace<L>,
+    plugin_paths: &[String],
+    language: &str,
+) -> LoadedPlugins {
+    let mut loaded = LoadedPlugins {
+        translations: Translations::new(language),
+        ..Default::default()
+    };
+    // Every plugin folder of every search root, roots in the order they were given
+    //
+    // Translations, saved blocks and errors refer to a plugin by its folder name, so only the first plugin with a name is loaded
+    let mut plugins: Vec<(String, String)> = Vec::new();
+    for path in plugin_paths {
+        let mut folders = file_system.get_folders_in_folder(path);
+        folders.sort();
+        for folder in folders {
+            let plugin_path = file_system.join(path, &folder);
+            if let Some((first, _)) = plugins.iter().find(|x| x.1 == folder) {
+                loaded.errors.push(PluginLoadError {
+                    plugin: folder,
+                    block: None,
+                    json_path: None,
+                    kind: PluginLoadErrorKind::DuplicatePluginName(first.clone()),
+                });
+                continue;
+            }
+            plugins.push((plugin_path, folder));
+        }
+    }
+    // All translations have to be known before any block is named, a block may use another plugin's key
+    for (plugin_path, plugin) in &plugins {
+        load_translations(file_system, plugin_path, plugin, &mut loaded);
+    }
+    for (plugin_path, plugin) in &plugins {
+        load_plugin(
+            file_system,
+            plugin_path,
+            plugin,
+            block_output_types,
+            block_output_colors,
+            font,
+            workspace,
+            &mut loaded,
+        );
+    }
+    loaded
+}
+
+fn read_file<F: FileSystem>(
+    file_system: &F,
+    path: &str,
+) -> Result<String, PluginLoadErrorKind> {
+    file_system
+        .get_file_contents(path)
+        .map_err(|x| x.to_string())
+        .and_then(|x| x.as_string().map_err(|x| x.to_string()))
+        .map_err(|x| PluginLoadErrorKind::MissingFile(path.to_string(), x))
+}
+
+fn load_translations<F: FileSystem>(
+    file_system: &F,
+    plugin_path: &str,
+    plugin: &str,
+    loaded: &mut LoadedPlugins,
+) {
+    let mut context = Context {
+        plugin: plugin.to_string(),
+        block: None,
+        errors: &mut loaded.errors,
+    };
+    let translation_file = file_system.join(plugin_path, "translation.csv");
+    match read_file(file_system, &translation_file).and_then(|x| {
+        crate::internal::svg::parse_translations(&x)
+            .map_err(|x| PluginLoadErrorKind::BadTranslations(x.to_string()))
+    }) {
+        Ok(parsed_translation) => {
+            for conflict in
+                loaded.translations.insert_plugin(plugin, parsed_translation)
+            {
+                context.push(
+                    None,
+                    PluginLoadErrorKind::TranslationConflict {
+                        key: conflict.key,
+                        kept_plugin: conflict.kept_plugin,
+                    },
+                );
+            }
+        }
+        Err(kind) => context.push(None, kind),
+    }
+}
+
+fn load_plugin<F: FileSystem, L: Physics>(
+    file_system: &F,
+    plugin_path: &str,
+    plugin: &str,
+    block_output_types: &mut Vec<String>,
+    block_output_colors: &mut Vec<u32>,
+    font: &Font,
+    workspace: &mut WorkSpace<L>,
+    loaded: &mut LoadedPlugins,
+) {
+    let mut context = Context {
+        plugin: plugin.to_string(),
+        block: None,
+        errors: &mut loaded.errors,
+    };
+    let setting_file = file_system.join(plugin_path, "settings.json");
+
+    let settings_json: Value = match read_file(file_system, &setting_file)
+        .and_then(|x| {
+            serde_json::from_str(&x)
+                .map_err(|x| PluginLoadErrorKind::BadJson(x.to_string()))
+        }) {
+        Ok(value) => value,
+        Err(kind) => {
+            context.push(None, kind);
+            return;
+        }
+    };
+    load_types(&settings_json, &mut loaded.types, &mut context);
+    let Some(pre_blocks) = settings_json.get("blocks") else {
+        context.missing("blocks".into(), "blocks");
+        return;
+    };
+    let Some(pre_blocks) = pre_blocks.as_array() else {
+        context.wrong_type("blocks".into(), "an array");
+        return;
+    };
+
+    for (block_idx, block) in pre_blocks.iter().enumerate() {
+        context.block = None;
+        let json_path = format!("blocks[{}]", block_idx);
+        let Some(mut block) =
+            load_block(block, &json_path, &loaded.translations, block_output_types, block_output_colors, font, workspace, &mut context)
+        else {
+            continue;
+        };
+        block.plugin = plugin.to_string();
+        translate_slots(&loaded.translations, &mut block);
+        if block.kind.goes_into_inputs() {
+            loaded.inline_blocks.push(block);
+        } else {
+            loaded.action_blocks.push(block);
+        }
+    }
+}
+
+/// `"types": {"integer": ["number"]}` - Optional
+fn load_types(settings_json: &Value, types: &mut TypeHierarchy, context: &mut Context) {
+    let Some(declared) = settings_json.get("types") else {
+        return;
+    };
+    let Some(declared) = declared.as_object() else {
+        context.wrong_type("types".into(), "an object of string arrays");
+        return;
+    };
+    for (child, parents) in declared {
+        let path = format!("types.{}", child);
+        let Some(parents) = parents.as_array() else {
+            context.wrong_type(path, "an array of strings");
+            continue;
+        };
+        let mut names = Vec::new();
+        for (idx, parent) in parents.iter().enumerate() {
+            match parent.as_str() {
+                Some(parent) => names.push(parent.to_string()),
+                None => context.wrong_type(format!("{}[{}]", path, idx), "a string"),
+            }
+        }
+        types.extend(child, &names);
+    }
+}
+
+fn get_string(
+    value: &Value,
+    key: &'static str,
+    json_path: &str,
+    context: &mut Context,
+) -> Option<String> {
+    let path = format!("{}.{}", json_path, key);
+    match value.get(key) {
+        None => {
+            context.missing(path, key);
+            None
+        }
+        Some(Value::String(string)) => Some(string.clone()),
+        Some(_) => {
+            context.wrong_type(path, "a string");
+            None
+        }
+    }
+}
+
+/// Missing is fine, anything other than a list of strings isn't
+fn get_string_list(
+    value: &Value,
+    key: &'static str,
+    json_path: &str,
+    context: &mut Context,
+) -> Option<Vec<String>> {
+    let path = format!("{}.{}", json_path, key);
+    let Some(list) = value.get(key) else {
+        return Some(Vec::new());
+    };
+    let Some(list) = list.as_array() else {
+        context.wrong_type(path, "an array of strings");
+        return None;
+    };
+    let mut strings = Vec::new();
+    for (idx, item) in list.iter().enumerate() {
+        let Some(item) = item.as_str() else {
+            context.wrong_type(format!("{}[{}]", path, idx), "a string");
+            return None;
+        };
+        strings.push(item.to_string());
+    }
+    Some(strings)
+}
+
+fn load_block<L: Physics>(
+    block: &Value,
+    json_path: &str,
+    translations: &Translations,
+    block_output_types: &mut Vec<String>,
+    block_output_colors: &mut Vec<u32>,
+    font: &Font,
+    workspace: &mut WorkSpace<L>,
+    context: &mut Context,
+) -> Option<Block> {
+    if !block.is_object() {
+        context.wrong_type(json_path.to_string(), "an object");
+        return None;
+    }
+    let internal_name = get_string(block, "name", json_path, context)?;
+    context.block = Some(internal_name.clone());
+
+    let block_type = get_string(block, "type", json_path, context)?;
+    let Some(kind) = BlockKind::from_name(&block_type) else {
+        context.push(
+            Some(format!("{}.type", json_path)),
+            PluginLoadErrorKind::UnsupportedBlockType(block_type),
+        );
+        return None;
+    };
+
+    // Collect every problem of this block before giving up on it
+    let output = get_string(block, "output", json_path, context);
+    let required_imports =
+        get_string_list(block, "required_imports", json_path, context);
+    let required_contexts =
+        get_string_list(block, "required_contexts", json_path, context);
+    let inputs = load_inputs(block, json_path, context);
+    let slots = get_string_list(block, "slots", json_path, context);
+    let (output, required_imports, required_contexts, mut inputs, slots) =
+        (output?, required_imports?, required_contexts?, inputs?, slots?);
+    for input in inputs.iter_mut() {
+        input.translate_labels(|key| translations.get(&context.plugin, key).cloned());
+    }
+    // Only containers have slots, and a container without any would just be an action
+    let slots: Vec<StatementSlot> = if kind.has_slots() {
+        if slots.is_empty() {
+            context.missing(format!("{}.slots", json_path), "slots");
+            return None;
+        }
+        slots.into_iter().map(StatementSlot::new).collect()
+    } else {
+        Vec::new()
+    };
+
+    if !block_output_types.contains(&output) {
+        block_output_types.push(output.clone());
+        block_output_colors.push(generate_random_color());
+    }
+
+    let name = match translations.get(&context.plugin, &internal_name) {
+        Some(name) => name.clone(),
+        None => {
+            context.push(
+                None,
+                PluginLoadErrorKind::UnknownTranslationKey(
+                    internal_name.clone(),
+                ),
+            );
+            untranslated_name(&internal_name, inputs.len())
+        }
+    };
+
+    match Block::new(
+        name,
+        internal_name,
+        0,
+        0,
+        kind,
+        required_imports,
+        required_contexts,
+        Vec::new(),
+        output,
+        inputs,
+        block_output_types,
+        font,
+        workspace,
+        None,
+    ) {
+        Ok(mut block) => {
+            block.set_slots(slots);
+            Some(block)
+        }
+        Err(error) => {
+            context.push(None, error.into());
+            None
+        }
+    }
+}
+
+/// Slots are labeled by translating their name
+fn translate_slots(translations: &Translations, block: &mut Block) {
+    for slot in block.slots.iter_mut() {
+        slot.label = translations
+            .get(&block.plugin, &slot.name)
+            .cloned()
+            .unwrap_or_else(|| slot.name.clone());
+    }
+}
+
+fn load_inputs(
+    block: &Value,
+    json_path: &str,
+    context: &mut Context,
+) -> Option<Vec<BlockInput>> {
+    let Some(pre_inputs) = block.get("inputs") else {
+        return Some(Vec::new());
+    };
+    let Some(pre_inputs) = pre_inputs.as_array() else {
+        context.wrong_type(format!("{}.inputs", json_path), "an array");
+        return None;
+    };
+
+    let mut inputs = Vec::new();
+    let mut valid = true;
+    for (input_idx, pre_input) in pre_inputs.iter().enumerate() {
+        let input_path = format!("{}.inputs[{}]", json_path, input_idx);
+        if !pre_input.is_object() {
+            context.wrong_type(input_path, "an object");
+            valid = false;
+            continue;
+        }
+        let input_type = get_string(pre_input, "type", &input_path, context);
+
+        let mut expected_literal_allowed = Vec::new();
+        let mut expected_literal_return = Vec::new();
+        if let Some(expected) = pre_input.get("expected") {
+            let expected_path = format!("{}.expected", input_path);
+            // Are you sure you didn't accidentally make it a list?
+            let Some(expected) = expected.as_object() else {
+                context.wrong_type(expected_path, "an object of strings");
+                valid = false;
+                continue;
+            };
+            for (key, value) in expected {
+                let Some(value) = value.as_str() else {
+                    context.wrong_type(
+                        format!("{}.{}", expected_path, key),
+                        "a string",
+                    );
+                    valid = false;
+                    continue;
+                };
+                expected_literal_allowed.push(key.clone());
+                expected_literal_return.push(value.to_string());
+            }
+        }
+
+        let Some(input_type) = input_type else {
+            valid = false;
+            continue;
+        };
+        inputs.push(
+            BlockInput::new(
+                input_type,
+                None,
+                expected_literal_allowed,
+                expected_literal_return,
+            )
+            .expect("Allowed literals and return values are pushed in pairs"),
+        );
+    }
+    if !valid {
+        return None;
+    }
+    Some(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logic::LogicFast;
+    use crate::test_support::{file_system, font, load_plugins, PLUGIN_ROOT};
+
+    /// Blocks listed in `plugins/example/settings.json`
+    const EXAMPLE_BLOCK_COUNT: usize = 17;
+
+    #[test]
+    fn example_plugin_loads_without_errors() {
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, _) = load_plugins(&mut workspace, &[PLUGIN_ROOT.to_string()], &font());
+        assert!(loaded.errors.is_empty(), "{:?}", loaded.errors);
+        assert_eq!(loaded.iter_definitions().count(), EXAMPLE_BLOCK_COUNT);
+    }
+
+    #[test]
+    fn plugin_names_are_loaded_once() {
+        let logic = LogicFast::new();
+        let mut workspace = WorkSpace::new(&logic);
+        let (loaded, _) = load_plugins(
+            &mut workspace,
+            &[PLUGIN_ROOT.to_string(), PLUGIN_ROOT.to_string()],
+            &font(),
+        );
+        assert_eq!(loaded.iter_definitions().count(), EXAMPLE_BLOCK_COUNT);
+        assert_eq!(loaded.errors.len(), 1);
+        assert!(matches!(loaded.errors[0].kind, PluginLoadErrorKind::DuplicatePluginName(_)));
+        assert_eq!(loaded.errors[0].plugin, "example");
+    }
+
+    #[test]
+    fn example_plugin_validates() {
+        let errors = validate::validate_plugin_path(&file_system(), &format!("{}/example", PLUGIN_ROOT));
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+}
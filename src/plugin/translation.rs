@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+/// Used for every key the chosen language doesn't translate
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// language -> key -> translated
+pub type TranslationTable = HashMap<String, HashMap<String, String>>;
+
+/// Translations of every loaded plugin, kept apart per plugin and merged into one global table
+///
+/// The global table holds the first plugin (in load order) that defined a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct Translations {
+    language: String,
+    /// plugin -> language -> key -> translated
+    per_plugin: HashMap<String, TranslationTable>,
+    /// language -> key -> (plugin, translated)
+    merged: HashMap<String, HashMap<String, (String, String)>>,
+}
+
+impl Default for Translations {
+    fn default() -> Self {
+        Self::new(FALLBACK_LANGUAGE)
+    }
+}
+
+/// Two plugins translate the same key differently
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationConflict {
+    pub key: String,
+    /// The plugin whose translation stays in the global table
+    pub kept_plugin: String,
+}
+
+impl Translations {
+    pub fn new(language: &str) -> Self {
+        Self {
+            language: language.to_string(),
+            per_plugin: HashMap::new(),
+            merged: HashMap::new(),
+        }
+    }
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+    pub fn set_language(&mut self, language: &str) {
+        self.language = language.to_string();
+    }
+    /// Every language at least one plugin has a column for, sorted
+    pub fn languages(&self) -> Vec<String> {
+        let mut languages: Vec<String> = self.merged.keys().cloned().collect();
+        languages.sort();
+        languages
+    }
+    /// Adds the table of one plugin, keys already defined differently by an earlier plugin are returned once each
+    pub fn insert_plugin(
+        &mut self,
+        plugin: &str,
+        table: TranslationTable,
+    ) -> Vec<TranslationConflict> {
+        let mut conflicts: Vec<TranslationConflict> = Vec::new();
+        let mut languages: Vec<&String> = table.keys().collect();
+        // Stable warning order
+        languages.sort();
+        for language in languages {
+            let merged = self.merged.entry(language.clone()).or_default();
+            let mut keys: Vec<&String> = table[language].keys().collect();
+            keys.sort();
+            for key in keys {
+                let translated = &table[language][key];
+                match merged.get(key) {
+                    Some((kept_plugin, kept)) => {
+                        if kept != translated
+                            && kept_plugin != plugin
+                            && !conflicts.iter().any(|x| &x.key == key)
+                        {
+                            conflicts.push(TranslationConflict {
+                                key: key.clone(),
+                                kept_plugin: kept_plugin.clone(),
+                            });
+                        }
+                    }
+                    None => {
+                        merged.insert(
+                            key.clone(),
+                            (plugin.to_string(), translated.clone()),
+                        );
+                    }
+                }
+            }
+        }
+        let own = self.per_plugin.entry(plugin.to_string()).or_default();
+        for (language, keys) in table {
+            own.entry(language).or_default().extend(keys);
+        }
+        conflicts
+    }
+    /// The plugin's own translation in the chosen and then the fallback language, before any other plugin's in the same order
+    ///
+    /// Another plugin translating a shared key into the chosen language doesn't override what the plugin itself says
+    pub fn get(&self, plugin: &str, key: &str) -> Option<&String> {
+        let languages = [self.language.as_str(), FALLBACK_LANGUAGE];
+        let own = self.per_plugin.get(plugin);
+        languages
+            .iter()
+            .find_map(|language| own?.get(*language)?.get(key))
+            .or_else(|| {
+                languages
+                    .iter()
+                    .find_map(|language| Some(&self.merged.get(*language)?.get(key)?.1))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(language: &str, key: &str, translated: &str) -> TranslationTable {
+        HashMap::from([(
+            language.to_string(),
+            HashMap::from([(key.to_string(), translated.to_string())]),
+        )])
+    }
+
+    #[test]
+    fn own_plugin_is_preferred_over_other_languages_of_others() {
+        let mut translations = Translations::new("de");
+        translations.insert_plugin("english_only", table("en", "block_say", "Say"));
+        translations.insert_plugin("german", table("de", "block_say", "Sage"));
+
+        assert_eq!(translations.get("english_only", "block_say").unwrap(), "Say");
+        assert_eq!(translations.get("german", "block_say").unwrap(), "Sage");
+        // Plugins without the key get the chosen language of whoever has it
+        assert_eq!(translations.get("other", "block_say").unwrap(), "Sage");
+        assert_eq!(translations.get("german", "block_missing"), None);
+    }
+}
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+/// Inputs of this type accept every block
+pub const ANY_TYPE: &str = "any";
+
+/// Which output types are assignable to which input types, declared by the plugins under `"types"`
+///
+/// `{"integer": ["number"]}` lets integer blocks go into number inputs - Assignability is transitive
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeHierarchy {
+    /// type -> types it may be used as
+    parents: HashMap<String, Vec<String>>,
+}
+
+impl TypeHierarchy {
+    /// Adds what one plugin declares, declarations of the same type by several plugins are merged
+    pub fn extend(&mut self, child: &str, parents: &[String]) {
+        let known = self.parents.entry(child.to_string()).or_default();
+        for parent in parents {
+            if !known.contains(parent) {
+                known.push(parent.clone());
+            }
+        }
+    }
+    /// Can a block with this output be stored in an input of `input_type`
+    pub fn is_assignable(&self, output: &str, input_type: &str) -> bool {
+        if input_type == ANY_TYPE {
+            return true;
+        }
+        // Walks up every declared parent, the visited list keeps cyclic declarations from looping forever
+        let mut visited = Vec::new();
+        let mut queue = Vec::from([output]);
+        while let Some(current) = queue.pop() {
+            if current == input_type {
+                return true;
+            }
+            if visited.contains(&current) {
+                continue;
+            }
+            visited.push(current);
+            if let Some(parents) = self.parents.get(current) {
+                queue.extend(parents.iter().map(String::as_str));
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+
+use mirl::platform::FileSystem;
+use serde_json::Value;
+
+use crate::internal::BlockKind;
+use crate::plugin::{PluginLoadError, PluginLoadErrorKind};
+
+/// The formal description of a plugin's `settings.json`
+pub const PLUGIN_SCHEMA: &str =
+    include_str!("../../schema/plugin_settings.schema.json");
+
+/// Checks a plugin folder without loading anything into a workspace
+///
+/// A folder without a `settings.json` is treated as a search root and every plugin inside it is checked
+pub fn validate_plugin_path<F: FileSystem>(
+    file_system: &F,
+    path: &str,
+) -> Vec<PluginLoadError> {
+    if file_system.does_file_exist(&file_system.join(path, "settings.json")) {
+        return validate_plugin(file_system, path);
+    }
+    let mut errors = Vec::new();
+    let mut folders = file_system.get_folders_in_folder(path);
+    folders.sort();
+    for folder in folders {
+        errors.extend(validate_plugin(
+            file_system,
+            &file_system.join(path, &folder),
+        ));
+    }
+    errors
+}
+
+pub fn validate_plugin<F: FileSystem>(
+    file_system: &F,
+    plugin_path: &str,
+) -> Vec<PluginLoadError> {
+    let plugin = std::path::Path::new(plugin_path)
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or(plugin_path.to_string());
+    let mut errors = Vec::new();
+    let mut push = |block: Option<&str>, json_path: Option<String>, kind| {
+        errors.push(PluginLoadError {
+            plugin: plugin.clone(),
+            block: block.map(String::from),
+            json_path,
+            kind,
+        })
+    };
+
+    let settings_file = file_system.join(plugin_path, "settings.json");
+    let settings: Value = match super::read_file(file_system, &settings_file)
+        .and_then(|x| {
+            serde_json::from_str(&x)
+                .map_err(|x| PluginLoadErrorKind::BadJson(x.to_string()))
+        }) {
+        Ok(value) => value,
+        Err(kind) => {
+            push(None, None, kind);
+            return errors;
+        }
+    };
+    let schema: Value =
+        serde_json::from_str(PLUGIN_SCHEMA).expect("The bundled schema is valid json");
+    let mut violations = Vec::new();
+    check_schema(&settings, &schema, "", &mut violations);
+    for (json_path, message) in violations {
+        push(
+            None,
+            Some(json_path),
+            PluginLoadErrorKind::SchemaViolation(message),
+        );
+    }
+
+    let translation_file = file_system.join(plugin_path, "translation.csv");
+    let translation = match super::read_file(file_system, &translation_file)
+        .and_then(|x| {
+            crate::internal::svg::parse_translations(&x).map_err(|x| {
+                PluginLoadErrorKind::BadTranslations(x.to_string())
+            })
+        }) {
+        Ok(translation) => Some(translation),
+        Err(kind) => {
+            push(None, None, kind);
+            None
+        }
+    };
+
+    let empty = Vec::new();
+    let blocks = settings
+        .get("blocks")
+        .and_then(Value::as_array)
+        .unwrap_or(&empty);
+    // Malformed declarations were already reported by the schema
+    let mut types = super::TypeHierarchy::default();
+    if let Some(declared) = settings.get("types").and_then(Value::as_object) {
+        for (child, parents) in declared {
+            let parents: Vec<String> = parents
+                .as_array()
+                .unwrap_or(&empty)
+                .iter()
+                .filter_map(Value::as_str)
+                .map(String::from)
+                .collect();
+            types.extend(child, &parents);
+        }
+    }
+    let mut first_seen: HashMap<&str, usize> = HashMap::new();
+    let mut accepted_types = Vec::new();
+    for block in blocks {
+        for input in block
+            .get("inputs")
+            .and_then(Value::as_array)
+            .unwrap_or(&empty)
+        {
+            if let Some(input_type) = input.get("type").and_then(Value::as_str) {
+                accepted_types.push(input_type);
+            }
+        }
+    }
+
+    for (idx, block) in blocks.iter().enumerate() {
+        let json_path = format!("blocks[{}]", idx);
+        // Schema violations were already reported above
+        let Some(name) = block.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        if let Some(first) = first_seen.get(name) {
+            push(
+                Some(name),
+                Some(json_path.clone()),
+                PluginLoadErrorKind::DuplicateBlockName(format!("blocks[{}]", first)),
+            );
+        } else {
+            first_seen.insert(name, idx);
+        }
+
+        let input_count = block
+            .get("inputs")
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len);
+        if let Some(translation) = &translation {
+            let mut languages: Vec<&String> = translation.keys().collect();
+            languages.sort();
+            let mut translated_at_all = false;
+            // Every language has to fit, the user may switch to any of them
+            for language in languages {
+                let Some(translated) = translation[language].get(name) else {
+                    continue;
+                };
+                translated_at_all = true;
+                let placeholders = translated.matches("{}").count();
+                if placeholders != input_count {
+                    push(
+                        Some(name),
+                        Some(format!("translation.csv [{}]", language)),
+                        PluginLoadErrorKind::InputCountMismatch {
+                            placeholders,
+                            inputs: input_count,
+                        },
+                    );
+                }
+            }
+            if !translated_at_all {
+                push(
+                    Some(name),
+                    Some(json_path.clone()),
+                    PluginLoadErrorKind::UnknownTranslationKey(name.to_string()),
+                );
+            }
+            // Dropdown options are labeled by translating their key
+            let inputs = block.get("inputs").and_then(Value::as_array).unwrap_or(&empty);
+            for (input_idx, input) in inputs.iter().enumerate() {
+                let Some(expected) = input.get("expected").and_then(Value::as_object) else {
+                    continue;
+                };
+                for key in expected.keys() {
+                    if !translation.values().any(|x| x.contains_key(key)) {
+                        push(
+                            Some(name),
+                            Some(format!("{}.inputs[{}].expected.{}", json_path, input_idx, key)),
+                            PluginLoadErrorKind::UnknownTranslationKey(key.clone()),
+                        );
+                    }
+                }
+            }
+            // So are the slots of containers
+            let slots = block.get("slots").and_then(Value::as_array).unwrap_or(&empty);
+            for (slot_idx, slot) in slots.iter().enumerate() {
+                let Some(slot) = slot.as_str() else {
+                    continue;
+                };
+                if !translation.values().any(|x| x.contains_key(slot)) {
+                    push(
+                        Some(name),
+                        Some(format!("{}.slots[{}]", json_path, slot_idx)),
+                        PluginLoadErrorKind::UnknownTranslationKey(slot.to_string()),
+                    );
+                }
+            }
+        }
+
+        // Stacked blocks aren't put into inputs, so nothing needs to accept their output
+        let kind = block.get("type").and_then(Value::as_str).and_then(BlockKind::from_name);
+        if !kind.is_some_and(BlockKind::goes_into_inputs) {
+            continue;
+        }
+        if let Some(output) = block.get("output").and_then(Value::as_str) {
+            if !accepted_types.iter().any(|x| types.is_assignable(output, x)) {
+                push(
+                    Some(name),
+                    Some(format!("{}.output", json_path)),
+                    PluginLoadErrorKind::UnacceptedOutputType(output.to_string()),
+                );
+            }
+        }
+    }
+    errors
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(x) if x.is_i64() || x.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn join_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        return key.to_string();
+    }
+    format!("{}.{}", path, key)
+}
+
+/// Only understands the keywords our schema uses: type, enum, required, properties, additionalProperties, items and minLength
+pub fn check_schema(
+    value: &Value,
+    schema: &Value,
+    path: &str,
+    violations: &mut Vec<(String, String)>,
+) {
+    let shown_path = if path.is_empty() { "(root)" } else { path };
+    if let Some(expected) = schema.get("type") {
+        let actual = type_name(value);
+        let allowed: Vec<&str> = match expected {
+            Value::String(x) => vec![x.as_str()],
+            Value::Array(x) => x.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        let matches = allowed
+            .iter()
+            .any(|x| *x == actual || (*x == "number" && actual == "integer"));
+        if !matches {
+            violations.push((
+                shown_path.to_string(),
+                format!("expected {} but found {}", allowed.join(" or "), actual),
+            ));
+            return;
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.contains(value) {
+            let options: Vec<String> =
+                options.iter().map(|x| x.to_string()).collect();
+            violations.push((
+                shown_path.to_string(),
+                format!("{} is not one of {}", value, options.join(", ")),
+            ));
+        }
+    }
+    if let (Some(min_length), Value::String(string)) =
+        (schema.get("minLength").and_then(Value::as_u64), value)
+    {
+        if (string.chars().count() as u64) < min_length {
+            violations.push((
+                shown_path.to_string(),
+                format!("must be at least {} characters long", min_length),
+            ));
+        }
+    }
+    if let Value::Object(object) = value {
+        for required in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            if !object.contains_key(required) {
+                violations.push((
+                    shown_path.to_string(),
+                    format!("missing required field '{}'", required),
+                ));
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (key, item) in object {
+            let item_path = join_path(path, key);
+            if let Some(property) = properties.and_then(|x| x.get(key)) {
+                check_schema(item, property, &item_path, violations);
+                continue;
+            }
+            match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => violations.push((
+                    item_path,
+                    format!("unknown field '{}'", key),
+                )),
+                Some(additional @ Value::Object(_)) => {
+                    check_schema(item, additional, &item_path, violations)
+                }
+                _ => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items"))
+    {
+        for (idx, item) in items.iter().enumerate() {
+            check_schema(
+                item,
+                item_schema,
+                &format!("{}[{}]", path, idx),
+                violations,
+            );
+        }
+    }
+}
+
+/// `validate-plugin <folder>...` - Prints every problem, errors when at least one isn't just a warning
+pub fn validate_headless(
+    settings: &crate::settings::Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if settings.positional.is_empty() {
+        return Err("Usage: validate-plugin <plugin folder>...".into());
+    }
+    let file_system =
+        mirl::platform::file_system::NativeFileSystem::new(Vec::new())?;
+    let mut failed = 0;
+    for path in &settings.positional {
+        let path = crate::settings::absolute_path(path);
+        let errors = validate_plugin_path(&file_system, &path);
+        for error in &errors {
+            let level = if error.is_warning() { "warning" } else { "error" };
+            println!("{}: {}", level, error);
+        }
+        failed += errors.iter().filter(|x| !x.is_warning()).count();
+        if errors.is_empty() {
+            println!("'{}' is valid", path);
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} error(s) found", failed).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Extra plugin folders, separated like `PATH` is on the current os
+pub const PLUGIN_PATH_ENV: &str = "PROCEDURE_CRAFTER_PLUGINS";
+/// Looked for in the current directory, then next to the executable
+pub const SETTINGS_FILE: &str = "procedure_crafter.json";
+/// Folder of the example plugin shipped with the repository
+pub const BUNDLED_PLUGIN_FOLDER: &str = "plugins";
+
+/// Startup settings merged from the command line, the environment and the settings file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    /// `generate`, `validate-plugin`, ... - None opens the editor
+    pub command: Option<String>,
+    /// Everything that isn't a flag, in order
+    pub positional: Vec<String>,
+    /// Procedure file to open in the editor
+    pub open_file: Option<String>,
+    /// Plugin search roots, merged in this order
+    pub plugin_paths: Vec<String>,
+    /// Language column of the translation files to start with
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+pub struct SettingsFile {
+    /// Relative paths are relative to the settings file
+    #[serde(default)]
+    pub plugin_paths: Vec<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl Settings {
+    /// Parses `[command] [--plugins <folder>]... [--language <language>] [arguments]`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut settings = Settings::default();
+        let mut command_line_plugin_paths = Vec::new();
+        let mut command_line_language = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--plugins" | "-p" => {
+                    let path = args
+                        .next()
+                        .ok_or(format!("{} expects a folder after it", arg))?;
+                    command_line_plugin_paths.push(path.clone());
+                }
+                "--language" | "-l" => {
+                    let language = args
+                        .next()
+                        .ok_or(format!("{} expects a language after it", arg))?;
+                    command_line_language = Some(language.clone());
+                }
+                // Anything looking like an option that isn't one, a lone "-" is still an argument
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(format!("Unknown option '{}'", arg));
+                }
+                "generate" | "validate-plugin" | "bench" if settings.command.is_none() && settings.positional.is_empty() => {
+                    settings.command = Some(arg.clone());
+                }
+                _ => settings.positional.push(arg.clone()),
+            }
+        }
+        if settings.command.is_none() {
+            // Double clicking a .proc.json file passes its path as the first argument
+            settings.open_file = settings
+                .positional
+                .iter()
+                .find(|x| x.ends_with(crate::internal::save::PROCEDURE_FILE_EXTENSION))
+                .map(|x| absolute_path(x));
+        }
+        let settings_file = read_settings_file();
+        settings.plugin_paths =
+            collect_plugin_paths(&command_line_plugin_paths, settings_file.as_ref());
+        settings.language = command_line_language
+            .or(settings_file.and_then(|x| x.1.language))
+            .unwrap_or(crate::plugin::FALLBACK_LANGUAGE.to_string());
+        Ok(settings)
+    }
+}
+
+/// Command line first, then the environment variable, then the settings file - Duplicates are only kept once
+///
+/// Falls back to the bundled plugins when nothing was configured at all
+pub fn collect_plugin_paths(
+    command_line: &[String],
+    settings_file: Option<&(PathBuf, SettingsFile)>,
+) -> Vec<String> {
+    let mut paths = Vec::new();
+    for path in command_line {
+        push_unique(&mut paths, absolute_path(path));
+    }
+    if let Some(env) = std::env::var_os(PLUGIN_PATH_ENV) {
+        for path in std::env::split_paths(&env) {
+            if !path.as_os_str().is_empty() {
+                push_unique(&mut paths, absolute_path(&path.to_string_lossy()));
+            }
+        }
+    }
+    if let Some((folder, settings_file)) = settings_file {
+        for path in &settings_file.plugin_paths {
+            push_unique(&mut paths, folder.join(path).to_string_lossy().to_string());
+        }
+    }
+    if paths.is_empty() {
+        // Only the bundled folders that exist, unless none do so the error can list them
+        paths = bundled_plugin_paths();
+        if paths.iter().any(|x| Path::new(x).is_dir()) {
+            paths.retain(|x| Path::new(x).is_dir());
+        }
+    }
+    paths
+}
+
+/// Next to the executable for shipped builds, the repository itself for `cargo run`
+pub fn bundled_plugin_paths() -> Vec<String> {
+    let mut paths = Vec::new();
+    if let Some(exe_folder) = std::env::current_exe()
+        .ok()
+        .and_then(|x| x.parent().map(Path::to_path_buf))
+    {
+        push_unique(
+            &mut paths,
+            exe_folder.join(BUNDLED_PLUGIN_FOLDER).to_string_lossy().to_string(),
+        );
+    }
+    push_unique(
+        &mut paths,
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(BUNDLED_PLUGIN_FOLDER)
+            .to_string_lossy()
+            .to_string(),
+    );
+    paths
+}
+
+fn read_settings_file() -> Option<(PathBuf, SettingsFile)> {
+    let mut folders = Vec::new();
+    if let Ok(current) = std::env::current_dir() {
+        folders.push(current);
+    }
+    if let Some(exe_folder) = std::env::current_exe()
+        .ok()
+        .and_then(|x| x.parent().map(Path::to_path_buf))
+    {
+        folders.push(exe_folder);
+    }
+    for folder in folders {
+        let path = folder.join(SETTINGS_FILE);
+        let Ok(contents) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        match serde_json::from_str(&contents) {
+            Ok(settings_file) => return Some((folder, settings_file)),
+            Err(error) => {
+                eprintln!("Ignoring '{}': {}", path.display(), error)
+            }
+        }
+    }
+    None
+}
+
+/// Plugin roots split by whether they can actually be read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginPaths {
+    pub existing: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl PluginPaths {
+    /// Errors with every path that was tried when none of them exist
+    pub fn check(paths: &[String]) -> Result<Self, String> {
+        let (existing, missing): (Vec<String>, Vec<String>) =
+            paths.iter().cloned().partition(|x| Path::new(x).is_dir());
+        if existing.is_empty() {
+            let mut tried = String::new();
+            for path in &missing {
+                tried += &format!("\n    {}", path);
+            }
+            if tried.is_empty() {
+                tried = "\n    (none configured)".to_string();
+            }
+            return Err(format!(
+                "No plugin folder could be found. Tried:{}\nPass one with --plugins <folder>, set {} or list them as \"plugin_paths\" in {}",
+                tried, PLUGIN_PATH_ENV, SETTINGS_FILE
+            ));
+        }
+        Ok(Self { existing, missing })
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, item: String) {
+    if !list.contains(&item) {
+        list.push(item);
+    }
+}
+
+/// The file system resolves relative paths next to the executable, command line paths are meant relative to the shell
+pub fn absolute_path(path: &str) -> String {
+    std::path::absolute(path)
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or(path.to_string())
+}
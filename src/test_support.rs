@@ -0,0 +1,69 @@
+use fontdue::Font;
+use mirl::platform::{file_system::NativeFileSystem, FileSystem};
+
+use crate::internal::{WorkSpace, ID};
+use crate::logic::Physics;
+use crate::plugin::{load_blocks, LoadedPlugins, FALLBACK_LANGUAGE};
+
+/// Search root holding the bundled example plugin, which has to keep loading without errors
+pub const PLUGIN_ROOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/plugins");
+
+pub fn font() -> Font {
+    Font::from_bytes(include_bytes!("inter.ttf") as &[u8], fontdue::FontSettings::default())
+        .expect("The bundled font is valid")
+}
+
+pub fn file_system() -> NativeFileSystem {
+    NativeFileSystem::new(Vec::new()).expect("The native file system is always there")
+}
+
+/// Loads every plugin of the given search roots into `workspace`, returns them with the output color names blocks are duplicated with
+pub fn load_plugins<L: Physics>(
+    workspace: &mut WorkSpace<L>,
+    plugin_roots: &[String],
+    font: &Font,
+) -> (LoadedPlugins, Vec<String>) {
+    let mut output_color_names = Vec::new();
+    let loaded = load_blocks(
+        &file_system(),
+        &mut output_color_names,
+        &mut Vec::new(),
+        font,
+        workspace,
+        plugin_roots,
+        FALLBACK_LANGUAGE,
+    );
+    (loaded, output_color_names)
+}
+
+/// [`load_plugins`] with only the bundled example plugin
+pub fn load_example<L: Physics>(workspace: &mut WorkSpace<L>, font: &Font) -> (LoadedPlugins, Vec<String>) {
+    load_plugins(workspace, &[PLUGIN_ROOT.to_string()], font)
+}
+
+/// Adds a fresh copy of the loaded block named `name` to the workspace, returns its id
+pub fn add_block<L: Physics>(
+    workspace: &mut WorkSpace<L>,
+    loaded: &LoadedPlugins,
+    output_color_names: &[String],
+    font: &Font,
+    name: &str,
+) -> ID {
+    let definition = loaded
+        .iter_definitions()
+        .find(|x| x.internal_name == name)
+        .unwrap_or_else(|| panic!("The example plugin has no block named '{}'", name));
+    let block = definition.duplicate(output_color_names, font, workspace).unwrap();
+    let id = block.id;
+    workspace.blocks.push(block);
+    id
+}
+
+/// Puts `below` (and what is below it) under `above` like dropping it there does
+pub fn stack_below<L: Physics>(workspace: &WorkSpace<L>, above: ID, below: ID) {
+    let blocks = &workspace.blocks;
+    let above = blocks.index_of(&above).unwrap();
+    let below = blocks.get_by_id(&below).unwrap();
+    below.connect_to_block(above, blocks);
+    below.update_topmost(blocks, true);
+}